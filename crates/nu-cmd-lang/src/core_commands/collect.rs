@@ -10,6 +10,115 @@ use nu_protocol::{
 #[derive(Clone)]
 pub struct Collect;
 
+impl Collect {
+    /// Run the closure once per batch instead of collecting the whole stream into one value
+    /// first. The caller only gets here when at least one of `chunk_size` and `window_size` is
+    /// `Some`: `chunk_size` runs the closure once per disjoint batch of that many values, while
+    /// `window_size` runs it once per overlapping window, sliding forward by `stride` values each
+    /// time. Giving both is not rejected: the batch length then comes from `chunk_size`, and the
+    /// batches slide by `stride` as windows do.
+    ///
+    /// The stream itself is never fully materialized - only one batch at a time is buffered -
+    /// but the closure still has to be run synchronously here to produce each batch's output, so
+    /// the results end up collected into a single list rather than streamed back out lazily.
+    #[allow(clippy::too_many_arguments)]
+    fn run_batched(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+        capture_block: Closure,
+        chunk_size: Option<usize>,
+        window_size: Option<usize>,
+        stride: usize,
+    ) -> Result<PipelineData, ShellError> {
+        let target_size = chunk_size
+            .or(window_size)
+            .expect("run_batched is only called when --chunks or --window was given");
+
+        let block = engine_state.get_block(capture_block.block_id).clone();
+        let mut stack_captures = stack.captures_to_stack(capture_block.captures.clone());
+        let positional_var = block
+            .signature
+            .get_positional(0)
+            .and_then(|var| var.var_id);
+
+        let eval_block = get_eval_block(engine_state);
+
+        let mut results = Vec::new();
+        let mut buffer: Vec<Value> = Vec::new();
+        // How many more incoming values to discard without buffering, to cover the part of a
+        // `--stride` larger than `--window` that the buffer drain below can't express on its own
+        // (draining can only ever remove what's already buffered, i.e. at most `target_size`).
+        let mut skip_before_next_window = 0usize;
+
+        let mut run_on = |stack_captures: &mut Stack, batch: Vec<Value>| -> Result<(), ShellError> {
+            let batch = Value::list(batch, call.head);
+            if let Some(var_id) = positional_var {
+                stack_captures.add_var(var_id, batch.clone());
+            }
+            let result = eval_block(
+                engine_state,
+                stack_captures,
+                &block,
+                batch.into_pipeline_data(),
+                call.redirect_stdout,
+                call.redirect_stderr,
+            )?;
+            results.push(result.into_value(call.head));
+            Ok(())
+        };
+
+        for value in input.into_iter() {
+            if skip_before_next_window > 0 {
+                skip_before_next_window -= 1;
+                continue;
+            }
+
+            buffer.push(value);
+            if buffer.len() == target_size {
+                if window_size.is_some() {
+                    // Bounded by `window_len` (the buffer's length right now, == `target_size`),
+                    // not `window_size` directly - `--chunks` and `--window` aren't actually
+                    // enforced as mutually exclusive above, so `target_size` can come from
+                    // `chunk_size` instead while `window_size` is still `Some` and larger than
+                    // the buffer actually is.
+                    let window_len = buffer.len();
+                    let drop_n = stride.min(window_len);
+                    run_on(&mut stack_captures, buffer.clone())?;
+                    buffer.drain(0..drop_n);
+                    // `stride` beyond the window's length is a gap the buffer itself can't
+                    // represent - those values need to never be buffered at all, not just be
+                    // drained early.
+                    skip_before_next_window = stride.saturating_sub(window_len);
+                } else {
+                    run_on(&mut stack_captures, std::mem::take(&mut buffer))?;
+                }
+            }
+        }
+
+        // A trailing partial chunk/window that never reached `target_size` still carries real
+        // values from the stream, so it gets a final run rather than being silently dropped.
+        if !buffer.is_empty() {
+            run_on(&mut stack_captures, buffer)?;
+        }
+
+        if call.has_flag(engine_state, stack, "keep-env")? {
+            redirect_env(engine_state, stack, &stack_captures);
+            for (var_id, _) in capture_block.captures {
+                stack_captures.remove_var(var_id);
+            }
+            if let Some(var_id) = positional_var {
+                stack_captures.remove_var(var_id);
+            }
+            stack.vars.extend(stack_captures.vars);
+        }
+
+        Ok(Value::list(results, call.head).into_pipeline_data())
+    }
+}
+
 impl Command for Collect {
     fn name(&self) -> &str {
         "collect"
@@ -23,6 +132,26 @@ impl Command for Collect {
                 SyntaxShape::Closure(Some(vec![SyntaxShape::Any])),
                 "The closure to run once the stream is collected.",
             )
+            .named(
+                "chunks",
+                SyntaxShape::Int,
+                "run the closure once per batch of this many values drawn lazily from the \
+                    stream, instead of collecting it all into one value first",
+                None,
+            )
+            .named(
+                "window",
+                SyntaxShape::Int,
+                "run the closure once per overlapping window of this many values, instead of \
+                    collecting the whole stream into one value first",
+                None,
+            )
+            .named(
+                "stride",
+                SyntaxShape::Int,
+                "how many values to advance between windows when using --window (default 1)",
+                None,
+            )
             .switch(
                 "keep-env",
                 "let the block affect environment variables",
@@ -44,9 +173,38 @@ impl Command for Collect {
     ) -> Result<PipelineData, ShellError> {
         let capture_block: Closure = call.req(engine_state, stack, 0)?;
 
+        let chunk_size = call
+            .get_flag::<i64>(engine_state, stack, "chunks")?
+            .map(|n| n.max(1) as usize);
+        let window_size = call
+            .get_flag::<i64>(engine_state, stack, "window")?
+            .map(|n| n.max(1) as usize);
+        let stride = call
+            .get_flag::<i64>(engine_state, stack, "stride")?
+            .map(|n| n.max(1) as usize)
+            .unwrap_or(1);
+
+        if chunk_size.is_some() || window_size.is_some() {
+            return self.run_batched(
+                engine_state,
+                stack,
+                call,
+                input,
+                capture_block,
+                chunk_size,
+                window_size,
+                stride,
+            );
+        }
+
         let block = engine_state.get_block(capture_block.block_id).clone();
         let mut stack_captures = stack.captures_to_stack(capture_block.captures.clone());
 
+        // `input.metadata()` only carries over what the producer already attached (e.g. a
+        // `DataSource::FilePath`); this version of the pipeline has no byte-stream variant that
+        // knows its own content type, so there's nothing to fold in here yet. Once one exists,
+        // this is where its content type should land: `metadata.get_or_insert_with(...)`-style,
+        // only filling `content_type` in when the metadata didn't already carry one.
         let metadata = input.metadata();
         let input: Value = input.into_value(call.head);
 
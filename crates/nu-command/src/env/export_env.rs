@@ -3,9 +3,18 @@ use nu_engine::{get_eval_block, redirect_env, CallExt};
 use nu_protocol::{
     ast::Call,
     engine::{Closure, Command, EngineState, Stack},
-    Category, Example, PipelineData, ShellError, Signature, Span, SyntaxShape, Type, Value,
+    Category, Example, PipelineData, Record, ShellError, Signature, Span, SyntaxShape, Type, Value,
 };
 
+/// Environment variable used to stack up `export-env --transient` rollback records in the current
+/// scope, so that nested transient blocks restore in LIFO order. This name is only ever written
+/// by `push_env_rollback`/`pop_env_rollback` below (and skipped by `env_rollback`) - chosen to be distinctive enough that it's
+/// unlikely to collide with a real environment variable, though it is still a plain `$env` entry
+/// under the hood and so will show up in something like `$env | columns`, and can be clobbered by
+/// a script that sets or hides it directly. There's no hidden-from-`$env` storage on `Stack` to
+/// put this in instead.
+const ROLLBACK_STACK_VAR: &str = "__NU_EXPORT_ENV_ROLLBACK_STACK";
+
 #[derive(Clone)]
 pub struct ExportEnv;
 
@@ -16,12 +25,18 @@ impl Command for ExportEnv {
 
     fn signature(&self) -> Signature {
         Signature::build("export-env")
-            .input_output_types(vec![(Type::Nothing, Type::Nothing)])
+            .input_output_types(vec![(Type::Nothing, Type::Any)])
             .required(
                 "block",
                 SyntaxShape::Block,
                 "The block to run to set the environment.",
             )
+            .switch(
+                "transient",
+                "Snapshot the caller's prior environment first, so `restore-env` can undo \
+                    exactly what this block changed.",
+                None,
+            )
             .category(Category::Env)
     }
 
@@ -29,6 +44,12 @@ impl Command for ExportEnv {
         "Run a block and preserve its environment in a current scope."
     }
 
+    fn extra_usage(&self) -> &str {
+        "With --transient, the previous value (or absence) of every environment variable the \
+            block touches is recorded before it's overwritten, so a later `restore-env` in the \
+            same scope can put things back exactly as they were."
+    }
+
     fn run(
         &self,
         engine_state: &EngineState,
@@ -36,13 +57,14 @@ impl Command for ExportEnv {
         call: &Call,
         input: PipelineData,
     ) -> Result<PipelineData, ShellError> {
+        let transient = call.has_flag(engine_state, caller_stack, "transient")?;
         let capture_block: Closure = call.req(engine_state, caller_stack, 0)?;
         let block = engine_state.get_block(capture_block.block_id);
         let mut callee_stack = caller_stack.captures_to_stack(capture_block.captures);
 
         let eval_block = get_eval_block(engine_state);
 
-        let _ = eval_block(
+        let result = eval_block(
             engine_state,
             &mut callee_stack,
             block,
@@ -51,9 +73,23 @@ impl Command for ExportEnv {
             call.redirect_stderr,
         );
 
+        // Compute the rollback record before anything else touches `caller_stack`'s environment,
+        // but don't push it until after `redirect_env` below. `redirect_env` copies
+        // `callee_stack`'s environment over `caller_stack`'s wholesale, including whatever
+        // (possibly stale, pre-nested-push) copy of `ROLLBACK_STACK_VAR` `callee_stack` is
+        // carrying, so pushing first and calling `redirect_env` after would let that copy clobber
+        // the frame we just pushed - pushing last guarantees our frame is what's left standing.
+        let rollback = transient.then(|| env_rollback(engine_state, caller_stack, &callee_stack));
+
+        // Preserve whatever environment the block managed to set up before failing, rather than
+        // discarding it along with the error.
         redirect_env(engine_state, caller_stack, &callee_stack);
 
-        Ok(PipelineData::empty())
+        if let Some(rollback) = rollback {
+            push_env_rollback(engine_state, caller_stack, call.head, rollback);
+        }
+
+        result
     }
 
     fn examples(&self) -> Vec<Example> {
@@ -68,10 +104,187 @@ impl Command for ExportEnv {
                 example: r#"export-env { $env.SPAM = 'eggs' }; $env.SPAM"#,
                 result: Some(Value::test_string("eggs")),
             },
+            Example {
+                description: "Temporarily override an environment variable, then put it back",
+                example: r#"export-env --transient { $env.SPAM = 'eggs' }; restore-env"#,
+                result: None,
+            },
         ]
     }
 }
 
+/// Compute a rollback record for `restore-env`: the prior value (or absence) of every key that
+/// `callee_stack`'s run of the block added, changed, or would otherwise leave different from what
+/// `caller_stack` had before it ran.
+///
+/// The record itself lives in the `ROLLBACK_STACK_VAR` environment variable (via
+/// `push_env_rollback`/`pop_env_rollback`), so it naturally drops with the scope if `restore-env`
+/// is never called - automatic restoration when a sibling scope ends, rather than only on an
+/// explicit call, would need to hook whatever already tears down a scope's stack, which isn't
+/// part of this module. `ROLLBACK_STACK_VAR` itself is excluded from the diff so a nested
+/// `export-env --transient` doesn't get its own still-pending rollback frame captured (and later
+/// wiped wholesale) as if it were a plain user-set variable.
+fn env_rollback(
+    engine_state: &EngineState,
+    caller_stack: &Stack,
+    callee_stack: &Stack,
+) -> Vec<(String, Option<Value>)> {
+    let before = caller_stack.get_env_vars(engine_state);
+    let after = callee_stack.get_env_vars(engine_state);
+
+    let added_or_changed = after
+        .keys()
+        .filter(|key| key.as_str() != ROLLBACK_STACK_VAR && after.get(*key) != before.get(*key));
+    let removed = before
+        .keys()
+        .filter(|key| key.as_str() != ROLLBACK_STACK_VAR && !after.contains_key(*key));
+
+    added_or_changed
+        .chain(removed)
+        .map(|key| {
+            let prior = before.get(key).cloned();
+            (key.clone(), prior)
+        })
+        .collect()
+}
+
+/// Push a rollback record onto the `ROLLBACK_STACK_VAR` stack kept in `stack`'s environment, so
+/// the matching `restore-env` can pop it back off again. Each entry is encoded as a record with a
+/// `key` and a `value` - the latter a zero- or one-element list, since a record can't otherwise
+/// represent the absence of a value the way `Option::None` does.
+fn push_env_rollback(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    span: Span,
+    rollback: Vec<(String, Option<Value>)>,
+) {
+    let entries = rollback
+        .into_iter()
+        .map(|(key, prior)| {
+            let mut record = Record::with_capacity(2);
+            record.insert("key".to_string(), Value::string(key, span));
+            record.insert(
+                "value".to_string(),
+                match prior {
+                    Some(value) => Value::list(vec![value], span),
+                    None => Value::list(vec![], span),
+                },
+            );
+            Value::record(record, span)
+        })
+        .collect();
+
+    let mut frames = stack
+        .get_env_var(engine_state, ROLLBACK_STACK_VAR)
+        .and_then(|value| value.into_list().ok())
+        .unwrap_or_default();
+    frames.push(Value::list(entries, span));
+
+    stack.add_env_var(ROLLBACK_STACK_VAR.to_string(), Value::list(frames, span));
+}
+
+/// Pop the most recently pushed rollback record off the `ROLLBACK_STACK_VAR` stack, removing the
+/// variable entirely once its last frame is popped. Returns `None` if there's no frame to pop.
+fn pop_env_rollback(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+) -> Option<Vec<(String, Option<Value>)>> {
+    let rollback_var = stack.get_env_var(engine_state, ROLLBACK_STACK_VAR)?;
+    let span = rollback_var.span();
+    let mut frames = rollback_var.into_list().ok()?;
+    let frame = frames.pop()?;
+
+    if frames.is_empty() {
+        stack.remove_env_var(engine_state, ROLLBACK_STACK_VAR);
+    } else {
+        stack.add_env_var(ROLLBACK_STACK_VAR.to_string(), Value::list(frames, span));
+    }
+
+    let entries = frame
+        .into_list()
+        .ok()?
+        .into_iter()
+        .filter_map(|entry| {
+            let record = entry.into_record().ok()?;
+            let mut key = None;
+            let mut prior = None;
+            for (field, value) in record {
+                match field.as_str() {
+                    "key" => key = value.as_str().ok().map(str::to_string),
+                    "value" => prior = value.into_list().ok().and_then(|mut vals| vals.pop()),
+                    _ => {}
+                }
+            }
+            Some((key?, prior))
+        })
+        .collect();
+
+    Some(entries)
+}
+
+/// Undo the most recent `export-env --transient` recorded in the current scope.
+#[derive(Clone)]
+pub struct RestoreEnv;
+
+impl Command for RestoreEnv {
+    fn name(&self) -> &str {
+        "restore-env"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("restore-env")
+            .input_output_types(vec![(Type::Nothing, Type::Nothing)])
+            .category(Category::Env)
+    }
+
+    fn usage(&self) -> &str {
+        "Revert the environment changes made by the most recent `export-env --transient`."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Pops the rollback record pushed by the matching `export-env --transient` and restores \
+            each key it touched to its prior value, or removes it entirely if it wasn't set \
+            before. Errors if there's no such record in the current scope - this isn't meant to \
+            pair with a plain `export-env`, which never pushes one."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let rollback =
+            pop_env_rollback(engine_state, stack).ok_or_else(|| ShellError::GenericError {
+                error: "No transient environment to restore".into(),
+                msg: "there's no recorded `export-env --transient` snapshot in this scope".into(),
+                span: Some(call.head),
+                help: Some("call `export-env --transient { ... }` before `restore-env`".into()),
+                inner: vec![],
+            })?;
+
+        for (key, prior) in rollback {
+            match prior {
+                Some(value) => stack.add_env_var(key, value),
+                None => {
+                    stack.remove_env_var(engine_state, &key);
+                }
+            }
+        }
+
+        Ok(PipelineData::empty())
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![Example {
+            description: "Override SPAM for one task, then restore it",
+            example: r#"export-env --transient { $env.SPAM = 'eggs' }; restore-env"#,
+            result: None,
+        }]
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -82,4 +295,11 @@ mod test {
 
         test_examples(ExportEnv {})
     }
+
+    #[test]
+    fn test_restore_env_examples() {
+        use crate::test_examples;
+
+        test_examples(RestoreEnv {})
+    }
 }
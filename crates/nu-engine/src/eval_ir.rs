@@ -1,4 +1,8 @@
-use std::{fs::File, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs::File,
+    sync::{atomic::AtomicBool, Arc, Mutex, OnceLock},
+};
 
 use nu_path::expand_path_with;
 use nu_protocol::{
@@ -24,6 +28,13 @@ pub fn eval_ir_block<D: DebugContext>(
 
         let block_span = block.span;
 
+        // Ideally the passes in `optimize_ir_block` would run exactly once, right after
+        // compilation, with the result cached on `block.ir_block` itself - but that compilation
+        // step lives in the crate that lowers the AST to IR, not here, so there's nowhere in this
+        // crate to cache the result. Memoize it here instead, keyed by `block`'s own address -
+        // see `cached_optimized_ir_block` for why that's safe to key on.
+        let ir_block = cached_optimized_ir_block(block, ir_block);
+
         let args_base = stack.argument_stack.get_base();
         let mut registers = stack.register_buf_cache.acquire(ir_block.register_count);
 
@@ -36,9 +47,11 @@ pub fn eval_ir_block<D: DebugContext>(
                 redirect_out: None,
                 redirect_err: None,
                 registers: &mut registers[..],
+                call_frames: Vec::new(),
+                ctrlc: engine_state.ctrlc.clone(),
             },
             &block_span,
-            ir_block,
+            &ir_block,
             input,
         );
 
@@ -71,8 +84,21 @@ struct EvalContext<'a> {
     /// State set by redirect-err
     redirect_err: Option<Redirection>,
     registers: &'a mut [PipelineData],
+    /// Calls currently in progress within this block, used to attach a backtrace to errors that
+    /// propagate out of them. Bounded by [`MAX_BACKTRACE_FRAMES`] so deep recursion doesn't grow
+    /// this (or the errors it produces) without limit.
+    call_frames: Vec<(Span, DeclId)>,
+    /// Set by Ctrl-C; checked by `eval_iterate` so IR-level loops over long or infinite streams
+    /// can be cancelled.
+    ctrlc: Option<Arc<AtomicBool>>,
 }
 
+/// Most backtrace frames (see [`attach_backtrace_frame`]) that a propagating error gains from the
+/// calls it passes through, and the most [`EvalContext::call_frames`] entries kept at once. An
+/// error that passes through more calls than this still propagates normally; it just stops
+/// gaining new frames.
+const MAX_BACKTRACE_FRAMES: usize = 16;
+
 impl<'a> EvalContext<'a> {
     /// Replace the contents of a register with a new value
     fn put_reg(&mut self, reg_id: RegId, new_value: PipelineData) -> PipelineData {
@@ -443,7 +469,7 @@ fn eval_instruction(
             dst,
             stream,
             end_index,
-        } => eval_iterate(ctx, *dst, *stream, *end_index),
+        } => eval_iterate(ctx, *dst, *stream, *end_index, *span),
         Instruction::Return { src } => Ok(Return(*src)),
     }
 }
@@ -647,10 +673,50 @@ fn eval_call(
     };
 
     // Run the call
+    let record_frame = ctx.call_frames.len() < MAX_BACKTRACE_FRAMES;
+    if record_frame {
+        ctx.call_frames.push((head, decl_id));
+    }
     let result = decl.run(engine_state, &mut stack, &(&call).into(), input);
     // Important that this runs:
     stack.argument_stack.leave_frame(ctx.args_base);
-    result
+    if record_frame {
+        ctx.call_frames.pop();
+    }
+
+    result.map_err(|err| {
+        // Count the frames `err` already gained in nested calls, each of which ran in its own
+        // `EvalContext` and so never shows up in this context's `call_frames`.
+        let mut depth = 0;
+        let mut current = &err;
+        while let ShellError::GenericError { error, inner, .. } = current {
+            match inner.first() {
+                Some(next) if error.starts_with("error while running `") => {
+                    depth += 1;
+                    current = next;
+                }
+                _ => break,
+            }
+        }
+        if record_frame && depth < MAX_BACKTRACE_FRAMES {
+            attach_backtrace_frame(err, decl.name(), head)
+        } else {
+            err
+        }
+    })
+}
+
+/// Wraps `err` with a note that it happened while running `decl_name`, so that an error
+/// propagating up through several nested IR calls ends up with one backtrace-style frame per
+/// call still within [`MAX_BACKTRACE_FRAMES`] of the point where it was raised.
+fn attach_backtrace_frame(err: ShellError, decl_name: &str, call_span: Span) -> ShellError {
+    ShellError::GenericError {
+        error: format!("error while running `{decl_name}`"),
+        msg: "call originated here".into(),
+        span: Some(call_span),
+        help: None,
+        inner: vec![err],
+    }
 }
 
 /// Get variable from [`Stack`] or [`EngineState`]
@@ -683,7 +749,22 @@ fn eval_redirection(
         RedirectMode::Capture => Ok(Redirection::Pipe(OutDest::Capture)),
         RedirectMode::Null => Ok(Redirection::Pipe(OutDest::Null)),
         RedirectMode::Inherit => Ok(Redirection::Pipe(OutDest::Inherit)),
-        RedirectMode::File { path, append } => {
+        RedirectMode::File {
+            path,
+            append,
+            shared,
+        } => {
+            // When `shared` is set, the stderr redirection is being wired to reuse whatever
+            // file the stdout redirection already opened (e.g. `2>&1 > out.log`), rather than
+            // opening the same path a second time - two independent `File::open` calls would
+            // each truncate/seek on their own and interleave unpredictably. There's no register
+            // slot for open file handles in this engine, so the handle is found the same place
+            // it was stashed when stdout's redirection ran: `ctx.redirect_out`.
+            if shared.is_some() {
+                if let Some(Redirection::File(handle)) = &ctx.redirect_out {
+                    return Ok(Redirection::File(handle.clone()));
+                }
+            }
             let path = ctx.collect_reg(*path, span)?;
             let file = File::options()
                 .write(true)
@@ -692,6 +773,30 @@ fn eval_redirection(
                 .map_err(|err| err.into_spanned(span))?;
             Ok(Redirection::File(file.into()))
         }
+        RedirectMode::Tee { path, append } => {
+            // A real tee needs to write every byte to both the file and whatever the pipeline's
+            // `OutDest` is, which means the writer behind the redirection has to fan out to two
+            // sinks at once. `Redirection` itself only has variants for "write to this file" or
+            // "forward to this `OutDest`", never both - and `Redirection` isn't defined anywhere
+            // in this crate to add a boxed-writer variant to, so there's no way to actually wire
+            // such a fan-out writer in here. Opening the file and returning
+            // `Redirection::File` (as this used to do) would silently behave like a plain `>`
+            // redirect, dropping whatever the pipeline's `OutDest` would otherwise have seen -
+            // that's a worse bug than refusing to run, so this fails loudly instead.
+            let _ = ctx.collect_reg(*path, span)?;
+            let _ = append;
+            Err(ShellError::GenericError {
+                error: "`tee`-style redirection is not supported".into(),
+                msg: "this build can't fan a redirect out to both a file and the pipeline".into(),
+                span: Some(span),
+                help: Some(
+                    "redirect to the file only (`> path`), or pipe to `save path` instead, which \
+                        reads the stream once and writes it to disk without needing this"
+                        .into(),
+                ),
+                inner: vec![],
+            })
+        }
     }
 }
 
@@ -701,7 +806,12 @@ fn eval_iterate(
     dst: RegId,
     stream: RegId,
     end_index: usize,
+    span: Span,
 ) -> Result<InstructionResult, ShellError> {
+    if nu_utils::ctrl_c::was_pressed(&ctx.ctrlc) {
+        return Err(ShellError::InterruptedByUser { span });
+    }
+
     let mut data = ctx.take_reg(stream);
     if let PipelineData::ListStream(list_stream, _) = &mut data {
         // Modify the stream, taking one value off, and branching if it's empty
@@ -715,13 +825,1280 @@ fn eval_iterate(
         }
     } else {
         // Convert the PipelineData to an iterator, and wrap it in a ListStream so it can be
-        // iterated on
+        // iterated on, inheriting this context's interrupt signal so the stream stays
+        // cancellable wherever it ends up being consumed.
         let metadata = data.metadata();
-        let span = data.span().unwrap_or(Span::unknown());
+        let data_span = data.span().unwrap_or(Span::unknown());
         ctx.put_reg(
             stream,
-            PipelineData::ListStream(ListStream::new(data.into_iter(), span, None), metadata),
+            PipelineData::ListStream(
+                ListStream::new(data.into_iter(), data_span, ctx.ctrlc.clone()),
+                metadata,
+            ),
+        );
+        eval_iterate(ctx, dst, stream, end_index, span)
+    }
+}
+
+/// How many distinct `Block`s' optimized IR [`OPTIMIZED_IR_BLOCKS`] keeps memoized at once, so a
+/// long-running process that evaluates many distinct ad-hoc blocks over its lifetime (e.g. a REPL
+/// session, or a host embedding `nu-engine` that builds and drops many short-lived blocks) doesn't
+/// grow the cache without bound. There's nothing smarter than "forget everything once full" here
+/// (no real LRU tracking) - simple, and correctness never depends on what happens to still be
+/// cached, only on what's cheap to recompute when it isn't.
+const MAX_CACHED_IR_BLOCKS: usize = 4096;
+
+/// A cached [`optimize_ir_block`] result, along with a cheap structural fingerprint of the
+/// *unoptimized* [`IrBlock`] it was computed from. See [`cached_optimized_ir_block`] for why the
+/// fingerprint is checked rather than trusting the key alone.
+struct CachedIrBlock {
+    fingerprint: (usize, usize, usize),
+    optimized: Arc<IrBlock>,
+}
+
+/// Process-wide memo of [`optimize_ir_block`]'s output, keyed by the address of the [`Block`] it
+/// was run for. A `Block` is parsed once and then lives in its `EngineState`'s block table for the
+/// rest of that `EngineState`'s life - nothing ever removes or replaces one - so a `Block`'s
+/// address is a stable identity for as long as that `EngineState` (and the `Arc` it presumably
+/// holds the block in) is alive. It's not guaranteed unique for the life of the *process*, though:
+/// nothing stops a later, unrelated `Block` (in a different `EngineState`, e.g. a later test or a
+/// fresh REPL sub-evaluation) from being allocated at the same address once the first is freed.
+/// `CachedIrBlock::fingerprint` guards against that: a fingerprint mismatch on a key hit means
+/// this is actually a different block that happens to share an address, so it's treated as a
+/// fresh one (recomputed, and the stale entry overwritten) instead of silently handing back the
+/// wrong optimized IR.
+static OPTIMIZED_IR_BLOCKS: OnceLock<Mutex<HashMap<usize, CachedIrBlock>>> = OnceLock::new();
+
+/// A cheap (O(1), no instruction-by-instruction scan) stand-in for "is this plausibly the same
+/// `IrBlock` as before": not collision-proof, but along with the address already having to match,
+/// good enough that an actual collision would require two unrelated blocks with identical shape
+/// landing at the same freed address - astronomically less likely than an address collision alone.
+fn ir_block_fingerprint(ir_block: &IrBlock) -> (usize, usize, usize) {
+    (
+        ir_block.instructions.len(),
+        ir_block.register_count,
+        ir_block.data.len(),
+    )
+}
+
+/// Returns the optimized form of `block`'s compiled IR, running [`optimize_ir_block`] once per
+/// `Block` and reusing the result on every later call instead of recomputing it - compiling a
+/// closure's body once and then reusing that compiled form on every iteration, rather than
+/// reoptimizing a fresh copy of it on every single call.
+fn cached_optimized_ir_block(block: &Block, ir_block: &IrBlock) -> Arc<IrBlock> {
+    let key = block as *const Block as usize;
+    let fingerprint = ir_block_fingerprint(ir_block);
+    let cache = OPTIMIZED_IR_BLOCKS.get_or_init(|| Mutex::new(HashMap::new()));
+
+    {
+        let cache = cache.lock().expect("optimized IR block cache poisoned");
+        if let Some(cached) = cache.get(&key) {
+            if cached.fingerprint == fingerprint {
+                return cached.optimized.clone();
+            }
+        }
+    }
+
+    let mut optimized = ir_block.clone();
+    optimize_ir_block(&mut optimized);
+    let optimized = Arc::new(optimized);
+
+    let mut cache = cache.lock().expect("optimized IR block cache poisoned");
+    if cache.len() >= MAX_CACHED_IR_BLOCKS {
+        cache.clear();
+    }
+    cache.insert(
+        key,
+        CachedIrBlock {
+            fingerprint,
+            optimized: optimized.clone(),
+        },
+    );
+
+    optimized
+}
+
+/// Runs every peephole/dataflow pass in this module over `ir_block`, in the order that lets each
+/// one see the others' cleanup: constant folding first, so a literal `and`/`or` never reaches
+/// short-circuit lowering; then short-circuit lowering; then jump threading, to clean up the
+/// branches lowering just introduced; then dead-store elimination, now that folding and lowering
+/// have both had a chance to strand writes; and finally register compaction, once the instruction
+/// stream has reached its final shape and won't shift register lifetimes again.
+///
+/// Called once per `Block` via [`cached_optimized_ir_block`], which memoizes the result - see
+/// there for why this doesn't need to (and shouldn't) re-run on every invocation.
+pub(crate) fn optimize_ir_block(ir_block: &mut IrBlock) {
+    fold_constants(ir_block);
+    lower_short_circuit_boolean_ops(ir_block);
+    optimize_jumps(ir_block);
+    eliminate_dead_stores(ir_block);
+    compact_registers(ir_block);
+}
+
+/// Thread chains of unconditional jumps down to their final target, fold away branches that
+/// target their own fallthrough, and delete any instruction that's no longer reachable from the
+/// entry point.
+pub(crate) fn optimize_jumps(ir_block: &mut IrBlock) {
+    thread_jump_chains(&mut ir_block.instructions);
+    simplify_self_branches(&mut ir_block.instructions);
+    prune_unreachable(&mut ir_block.instructions, &mut ir_block.spans);
+}
+
+/// Follow a chain of unconditional [`Instruction::Jump`]s starting at `index` to find where it
+/// ultimately lands. Stops as soon as it revisits an index, so a jump cycle resolves to whichever
+/// instruction in the cycle it first returned to rather than looping forever.
+fn resolve_jump_chain(index: usize, instructions: &[Instruction]) -> usize {
+    let mut current = index;
+    let mut visited = std::collections::HashSet::new();
+    while visited.insert(current) {
+        match instructions.get(current) {
+            Some(Instruction::Jump { index: next }) if *next != current => current = *next,
+            _ => break,
+        }
+    }
+    current
+}
+
+fn thread_jump_chains(instructions: &mut [Instruction]) {
+    for pc in 0..instructions.len() {
+        match instructions[pc] {
+            Instruction::Jump { index } => {
+                instructions[pc] = Instruction::Jump {
+                    index: resolve_jump_chain(index, instructions),
+                };
+            }
+            Instruction::BranchIf { cond, index } => {
+                instructions[pc] = Instruction::BranchIf {
+                    cond,
+                    index: resolve_jump_chain(index, instructions),
+                };
+            }
+            Instruction::Iterate {
+                dst,
+                stream,
+                end_index,
+            } => {
+                instructions[pc] = Instruction::Iterate {
+                    dst,
+                    stream,
+                    end_index: resolve_jump_chain(end_index, instructions),
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replace any `BranchIf` whose target is just the next instruction with a `Drop` of the
+/// condition register - the branch never changes control flow, but the register still needs to
+/// be consumed.
+fn simplify_self_branches(instructions: &mut [Instruction]) {
+    for pc in 0..instructions.len() {
+        if let Instruction::BranchIf { cond, index } = instructions[pc] {
+            if index == pc + 1 {
+                instructions[pc] = Instruction::Drop { src: cond };
+            }
+        }
+    }
+}
+
+/// Delete instructions that can't be reached from pc 0, remapping every surviving branch target
+/// to account for the shift. Also sweeps up unconditional jumps-to-fallthrough, which have no
+/// observable effect once jump chains have been threaded.
+fn prune_unreachable(instructions: &mut Vec<Instruction>, spans: &mut Vec<Span>) {
+    let len = instructions.len();
+    let mut reachable = vec![false; len];
+    let mut stack = vec![0usize];
+    while let Some(pc) = stack.pop() {
+        if pc >= len || reachable[pc] {
+            continue;
+        }
+        reachable[pc] = true;
+        match &instructions[pc] {
+            Instruction::Jump { index } => stack.push(*index),
+            Instruction::BranchIf { index, .. } => {
+                stack.push(*index);
+                stack.push(pc + 1);
+            }
+            Instruction::Iterate { end_index, .. } => {
+                stack.push(*end_index);
+                stack.push(pc + 1);
+            }
+            Instruction::Return { .. } => {}
+            _ => stack.push(pc + 1),
+        }
+    }
+
+    for pc in 0..len {
+        if reachable[pc] {
+            if let Instruction::Jump { index } = instructions[pc] {
+                if index == pc + 1 {
+                    reachable[pc] = false;
+                }
+            }
+        }
+    }
+
+    if reachable.iter().all(|&r| r) {
+        return;
+    }
+
+    let deleted: Vec<bool> = reachable.iter().map(|&r| !r).collect();
+    compact_marked(instructions, spans, &deleted);
+}
+
+/// Removes every instruction marked `true` in `deleted`, remapping the branch targets of the
+/// ones that remain to account for the shift.
+fn compact_marked(instructions: &mut Vec<Instruction>, spans: &mut Vec<Span>, deleted: &[bool]) {
+    let len = instructions.len();
+
+    let mut remap = vec![0usize; len];
+    let mut next = 0;
+    for (pc, remapped) in remap.iter_mut().enumerate() {
+        if !deleted[pc] {
+            *remapped = next;
+            next += 1;
+        }
+    }
+
+    let mut new_instructions = Vec::with_capacity(next);
+    let mut new_spans = Vec::with_capacity(next);
+    for pc in 0..len {
+        if deleted[pc] {
+            continue;
+        }
+        let mut instr = instructions[pc].clone();
+        match &mut instr {
+            Instruction::Jump { index } => *index = remap[*index],
+            Instruction::BranchIf { index, .. } => *index = remap[*index],
+            Instruction::Iterate { end_index, .. } => *end_index = remap[*end_index],
+            _ => {}
+        }
+        new_instructions.push(instr);
+        new_spans.push(spans[pc]);
+    }
+
+    *instructions = new_instructions;
+    *spans = new_spans;
+}
+
+/// Compacts an [`IrBlock`]'s register file by reusing registers whose previous occupant is
+/// already dead by the time a new one is first written, then remaps every instruction (and the
+/// block's `register_count`) onto the compacted ids.
+///
+/// Liveness comes from a backward dataflow over the block's control-flow graph (see
+/// [`compute_live_ranges`]), so a value that is read again after a loop's back edge stays live
+/// across the whole loop body, whether the loop is built from `Iterate` or from `Jump`/`BranchIf`.
+pub(crate) fn compact_registers(ir_block: &mut IrBlock) {
+    let ranges = compute_live_ranges(&ir_block.instructions, ir_block.register_count as usize);
+    let remap = allocate_registers(&ranges);
+
+    for instruction in &mut ir_block.instructions {
+        remap_instruction_registers(instruction, &remap);
+    }
+
+    let register_count = remap
+        .iter()
+        .map(|reg| reg.0)
+        .max()
+        .map(|max| max + 1)
+        .unwrap_or(0);
+    ir_block.register_count = register_count as _;
+}
+
+fn reg_index(reg_id: RegId) -> usize {
+    reg_id.0 as usize
+}
+
+/// Successor program counters of the instruction at `pc`, for dataflow analyses that need to walk
+/// the block's actual control-flow graph rather than just scanning in linear pc order. A backward
+/// `Jump`/`BranchIf` pair is just as much a loop back-edge as `Iterate` is, so all three get an
+/// edge to wherever they can transfer control. These are the edges [`compute_liveness`] walks
+/// for both [`eliminate_dead_stores`] and [`compute_live_ranges`].
+fn instruction_successors(instructions: &[Instruction], pc: usize) -> Vec<usize> {
+    match &instructions[pc] {
+        Instruction::Jump { index } => vec![*index],
+        Instruction::BranchIf { index, .. } => vec![*index, pc + 1],
+        Instruction::Iterate { end_index, .. } => vec![*end_index, pc + 1],
+        Instruction::Return { .. } => vec![],
+        _ => vec![pc + 1],
+    }
+}
+
+/// Backward liveness fixpoint dataflow over a block's control-flow graph: `live_in[pc][r]`/
+/// `live_out[pc][r]` are true if register `r` might still be read after `pc` runs (`live_out`) or
+/// at-or-after `pc` itself (`live_in`), accounting for every edge [`instruction_successors`]
+/// reports - including backward `Jump`/`BranchIf`/`Iterate` loop edges, so a value that's only
+/// read again after control flows back around a loop still comes out live across the whole loop
+/// body. Shared by [`eliminate_dead_stores`] and [`compute_live_ranges`], which both need exactly
+/// this computation and previously each ran their own separately-maintained copy of it.
+fn compute_liveness(instructions: &[Instruction], register_count: usize) -> LivenessResult {
+    let len = instructions.len();
+    let mut live_in = vec![vec![false; register_count]; len];
+    let mut live_out = vec![vec![false; register_count]; len];
+
+    loop {
+        let mut changed = false;
+        for pc in (0..len).rev() {
+            let mut out = vec![false; register_count];
+            for succ in instruction_successors(instructions, pc) {
+                if succ < len {
+                    for r in 0..register_count {
+                        out[r] |= live_in[succ][r];
+                    }
+                }
+            }
+            if out != live_out[pc] {
+                live_out[pc] = out;
+                changed = true;
+            }
+
+            let (reads, writes) = instruction_reads_writes(&instructions[pc]);
+            let mut inn = live_out[pc].clone();
+            if let Some(w) = writes {
+                inn[reg_index(w)] = false;
+            }
+            for r in reads {
+                inn[reg_index(r)] = true;
+            }
+            if inn != live_in[pc] {
+                live_in[pc] = inn;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    LivenessResult { live_in, live_out }
+}
+
+/// Per-pc live-register sets returned by [`compute_liveness`].
+struct LivenessResult {
+    live_in: Vec<Vec<bool>>,
+    live_out: Vec<Vec<bool>>,
+}
+
+/// First-use/last-use (inclusive instruction index) per register id.
+///
+/// Runs the same kind of backward liveness dataflow over the block's control-flow graph that
+/// [`eliminate_dead_stores`] does, rather than a linear scan with a special case bolted on for
+/// `Iterate`: a register whose live range only actually spans a loop body reached via a plain
+/// backward `Jump`/`BranchIf` (not `Iterate`) needs its range extended across that loop exactly
+/// the same way, or two such registers can end up coalesced onto the same physical register by
+/// [`allocate_registers`] even though their live ranges truly overlap across iterations.
+fn compute_live_ranges(
+    instructions: &[Instruction],
+    register_count: usize,
+) -> Vec<Option<(usize, usize)>> {
+    let len = instructions.len();
+    if len == 0 || register_count == 0 {
+        return vec![None; register_count];
+    }
+
+    let LivenessResult { live_in, live_out } = compute_liveness(instructions, register_count);
+
+    // A register's live range for allocation purposes is the span from the first pc where it's
+    // live (in or out) to the last - derived from the dataflow result rather than from raw
+    // instruction reads/writes, so ranges that cross a loop back-edge come out already extended
+    // across the whole loop.
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; register_count];
+    for pc in 0..len {
+        for r in 0..register_count {
+            if live_in[pc][r] || live_out[pc][r] {
+                ranges[r] = Some(match ranges[r] {
+                    Some((first, last)) => (first.min(pc), last.max(pc)),
+                    None => (pc, pc),
+                });
+            }
+        }
+    }
+
+    // A register that's written but never subsequently read (e.g. the loop variable of a `for`
+    // whose body ignores it - `Iterate`'s `dst` isn't eliminated by `eliminate_dead_stores` since
+    // the instruction itself still drives the iteration) never appears in any live-in/live-out
+    // set above, so it would otherwise come out of this function as `None`. `allocate_registers`
+    // treats `None` as "never used" and leaves such a register unmapped, which defaults to
+    // physical register 0 and silently clobbers whatever real value that register holds. Giving
+    // it a trivial `(pc, pc)` range here is enough to get it a real physical slot instead.
+    for pc in 0..len {
+        if let Some(w) = instruction_write_target(&instructions[pc]) {
+            let r = reg_index(w);
+            ranges[r] = Some(match ranges[r] {
+                Some((first, last)) => (first.min(pc), last.max(pc)),
+                None => (pc, pc),
+            });
+        }
+    }
+
+    ranges
+}
+
+/// Linear-scan register allocation over the computed live ranges, reusing a physical register as
+/// soon as its previous occupant's live range has ended.
+fn allocate_registers(ranges: &[Option<(usize, usize)>]) -> Vec<RegId> {
+    let mut order: Vec<usize> = (0..ranges.len()).filter(|&r| ranges[r].is_some()).collect();
+    order.sort_by_key(|&r| ranges[r].expect("filtered to Some above").0);
+
+    let mut remap = vec![RegId(0); ranges.len()];
+    let mut retired: Vec<(usize, u32)> = Vec::new();
+    let mut next_physical: u32 = 0;
+
+    for reg in order {
+        let (first, last) = ranges[reg].expect("filtered to Some above");
+
+        let physical = if let Some(pos) = retired.iter().position(|&(dead_at, _)| dead_at < first)
+        {
+            retired.swap_remove(pos).1
+        } else {
+            let physical = next_physical;
+            next_physical += 1;
+            physical
+        };
+
+        remap[reg] = RegId(physical);
+        retired.push((last, physical));
+    }
+
+    remap
+}
+
+fn remap_instruction_registers(instruction: &mut Instruction, remap: &[RegId]) {
+    let m = |id: &mut RegId| *id = remap[reg_index(*id)];
+    match instruction {
+        Instruction::LoadLiteral { dst, .. } => m(dst),
+        Instruction::Move { dst, src } => {
+            m(dst);
+            m(src);
+        }
+        Instruction::Clone { dst, src } => {
+            m(dst);
+            m(src);
+        }
+        Instruction::Collect { src_dst } => m(src_dst),
+        Instruction::Drop { src } => m(src),
+        Instruction::Drain { src } => m(src),
+        Instruction::LoadVariable { dst, .. } => m(dst),
+        Instruction::StoreVariable { src, .. } => m(src),
+        Instruction::LoadEnv { dst, .. } => m(dst),
+        Instruction::LoadEnvOpt { dst, .. } => m(dst),
+        Instruction::StoreEnv { src, .. } => m(src),
+        Instruction::PushPositional { src } => m(src),
+        Instruction::AppendRest { src } => m(src),
+        Instruction::PushFlag { .. } => {}
+        Instruction::PushNamed { src, .. } => m(src),
+        Instruction::RedirectOut { mode } | Instruction::RedirectErr { mode } => {
+            if let RedirectMode::File { path, .. } | RedirectMode::Tee { path, .. } = mode {
+                m(path);
+            }
+        }
+        Instruction::Call { src_dst, .. } => m(src_dst),
+        Instruction::ListPush { src_dst, item } => {
+            m(src_dst);
+            m(item);
+        }
+        Instruction::ListSpread { src_dst, items } => {
+            m(src_dst);
+            m(items);
+        }
+        Instruction::RecordInsert { src_dst, key, val } => {
+            m(src_dst);
+            m(key);
+            m(val);
+        }
+        Instruction::RecordSpread { src_dst, items } => {
+            m(src_dst);
+            m(items);
+        }
+        Instruction::Not { src_dst } => m(src_dst),
+        Instruction::BinaryOp { lhs_dst, rhs, .. } => {
+            m(lhs_dst);
+            m(rhs);
+        }
+        Instruction::FollowCellPath { src_dst, path } => {
+            m(src_dst);
+            m(path);
+        }
+        Instruction::CloneCellPath { dst, src, path } => {
+            m(dst);
+            m(src);
+            m(path);
+        }
+        Instruction::UpsertCellPath {
+            src_dst,
+            path,
+            new_value,
+        } => {
+            m(src_dst);
+            m(path);
+            m(new_value);
+        }
+        Instruction::Jump { .. } => {}
+        Instruction::BranchIf { cond, .. } => m(cond),
+        Instruction::Iterate { dst, stream, .. } => {
+            m(dst);
+            m(stream);
+        }
+        Instruction::Return { src } => m(src),
+    }
+}
+
+/// Folds a `BinaryOp` or `Not` into a single `LoadLiteral` when its operands are immediately
+/// preceding `LoadLiteral`s and evaluating the operation at compile time would succeed.
+///
+/// Operations that depend on runtime context (`RegexMatch`/`NotRegexMatch`, which need
+/// [`EngineState`] to build the pattern) are left alone, as are any operands whose literal kind
+/// expands against the current directory (`Filepath`/`Directory`/`GlobPattern`). Anything that
+/// would error at runtime - divide by zero, overflow, and so on - simply fails to fold, leaving
+/// the original instructions (and their error spans) intact.
+pub(crate) fn fold_constants(ir_block: &mut IrBlock) {
+    let data = ir_block.data.clone();
+    let len = ir_block.instructions.len();
+    let mut replacements: Vec<Option<Instruction>> = vec![None; len];
+    let mut deleted = vec![false; len];
+
+    for pc in 0..len {
+        match &ir_block.instructions[pc] {
+            Instruction::Not { src_dst } => {
+                if pc >= 1 {
+                    if let Instruction::LoadLiteral { dst, lit } = &ir_block.instructions[pc - 1] {
+                        if dst == src_dst {
+                            if let Some(Value::Bool { val, .. }) =
+                                literal_to_const_value(lit, &data, ir_block.spans[pc])
+                            {
+                                replacements[pc] = Some(Instruction::LoadLiteral {
+                                    dst: *src_dst,
+                                    lit: Literal::Bool(!val),
+                                });
+                                deleted[pc - 1] = true;
+                            }
+                        }
+                    }
+                }
+            }
+            Instruction::BinaryOp { lhs_dst, op, rhs } => {
+                if pc >= 2 {
+                    if let (
+                        Instruction::LoadLiteral {
+                            dst: lhs_src,
+                            lit: lhs_lit,
+                        },
+                        Instruction::LoadLiteral {
+                            dst: rhs_src,
+                            lit: rhs_lit,
+                        },
+                    ) = (&ir_block.instructions[pc - 2], &ir_block.instructions[pc - 1])
+                    {
+                        if lhs_src == lhs_dst && rhs_src == rhs {
+                            let lhs_val =
+                                literal_to_const_value(lhs_lit, &data, ir_block.spans[pc - 2]);
+                            let rhs_val =
+                                literal_to_const_value(rhs_lit, &data, ir_block.spans[pc - 1]);
+                            if let (Some(lhs_val), Some(rhs_val)) = (lhs_val, rhs_val) {
+                                if let Some(result) =
+                                    try_fold_binary_op(op, &lhs_val, &rhs_val, ir_block.spans[pc])
+                                {
+                                    if let Some(lit) = value_to_literal(&result) {
+                                        replacements[pc] = Some(Instruction::LoadLiteral {
+                                            dst: *lhs_dst,
+                                            lit,
+                                        });
+                                        deleted[pc - 2] = true;
+                                        deleted[pc - 1] = true;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (pc, replacement) in replacements.into_iter().enumerate() {
+        if let Some(instruction) = replacement {
+            ir_block.instructions[pc] = instruction;
+        }
+    }
+
+    if deleted.iter().any(|&d| d) {
+        compact_marked(&mut ir_block.instructions, &mut ir_block.spans, &deleted);
+    }
+}
+
+/// Reconstructs the [`Value`] a [`Literal`] would evaluate to, for the subset of literal kinds
+/// that are pure compile-time constants. Returns `None` for anything that depends on runtime
+/// context, such as the current directory or other registers.
+fn literal_to_const_value(lit: &Literal, data: &Arc<[u8]>, span: Span) -> Option<Value> {
+    match lit {
+        Literal::Bool(b) => Some(Value::bool(*b, span)),
+        Literal::Int(i) => Some(Value::int(*i, span)),
+        Literal::Float(f) => Some(Value::float(*f, span)),
+        Literal::Binary(bin) => Some(Value::binary(&data[*bin], span)),
+        Literal::String(s) | Literal::RawString(s) => std::str::from_utf8(&data[*s])
+            .ok()
+            .map(|s| Value::string(s, span)),
+        Literal::CellPath(path) => Some(Value::cell_path(CellPath::clone(path), span)),
+        Literal::Nothing => Some(Value::nothing(span)),
+        Literal::Block(_)
+        | Literal::Closure(_)
+        | Literal::Range { .. }
+        | Literal::List { .. }
+        | Literal::Record { .. }
+        | Literal::Filepath { .. }
+        | Literal::Directory { .. }
+        | Literal::GlobPattern { .. } => None,
+    }
+}
+
+/// The inverse of [`literal_to_const_value`] for the value kinds that a folded `BinaryOp`/`Not`
+/// can produce. Values that would need a fresh data slice to represent (strings, in particular,
+/// from `Append`) aren't handled, since there's nowhere cheap to put the new bytes.
+fn value_to_literal(value: &Value) -> Option<Literal> {
+    match value {
+        Value::Bool { val, .. } => Some(Literal::Bool(*val)),
+        Value::Int { val, .. } => Some(Literal::Int(*val)),
+        Value::Float { val, .. } => Some(Literal::Float(*val)),
+        Value::Nothing { .. } => Some(Literal::Nothing),
+        _ => None,
+    }
+}
+
+/// Attempts to evaluate a binary operation on two constant values, mirroring [`binary_op`].
+/// Returns `None` if the operation needs runtime context it doesn't have, or if it would fail.
+fn try_fold_binary_op(op: &Operator, lhs: &Value, rhs: &Value, span: Span) -> Option<Value> {
+    match op {
+        Operator::Comparison(Comparison::RegexMatch | Comparison::NotRegexMatch) => None,
+        Operator::Assignment(_) => None,
+        Operator::Comparison(cmp) => match cmp {
+            Comparison::Equal => lhs.eq(span, rhs, span).ok(),
+            Comparison::NotEqual => lhs.ne(span, rhs, span).ok(),
+            Comparison::LessThan => lhs.lt(span, rhs, span).ok(),
+            Comparison::GreaterThan => lhs.gt(span, rhs, span).ok(),
+            Comparison::LessThanOrEqual => lhs.lte(span, rhs, span).ok(),
+            Comparison::GreaterThanOrEqual => lhs.gte(span, rhs, span).ok(),
+            Comparison::In => lhs.r#in(span, rhs, span).ok(),
+            Comparison::NotIn => lhs.not_in(span, rhs, span).ok(),
+            Comparison::StartsWith => lhs.starts_with(span, rhs, span).ok(),
+            Comparison::EndsWith => lhs.ends_with(span, rhs, span).ok(),
+            Comparison::RegexMatch | Comparison::NotRegexMatch => unreachable!("handled above"),
+        },
+        Operator::Math(mat) => match mat {
+            Math::Plus => lhs.add(span, rhs, span).ok(),
+            Math::Append => None,
+            Math::Minus => lhs.sub(span, rhs, span).ok(),
+            Math::Multiply => lhs.mul(span, rhs, span).ok(),
+            Math::Divide => lhs.div(span, rhs, span).ok(),
+            Math::Modulo => lhs.modulo(span, rhs, span).ok(),
+            Math::FloorDivision => lhs.floor_div(span, rhs, span).ok(),
+            Math::Pow => lhs.pow(span, rhs, span).ok(),
+        },
+        Operator::Boolean(bl) => match bl {
+            Boolean::And => lhs.and(span, rhs, span).ok(),
+            Boolean::Or => lhs.or(span, rhs, span).ok(),
+            Boolean::Xor => lhs.xor(span, rhs, span).ok(),
+        },
+        Operator::Bits(bit) => match bit {
+            Bits::BitOr => lhs.bit_or(span, rhs, span).ok(),
+            Bits::BitXor => lhs.bit_xor(span, rhs, span).ok(),
+            Bits::BitAnd => lhs.bit_and(span, rhs, span).ok(),
+            Bits::ShiftLeft => lhs.bit_shl(span, rhs, span).ok(),
+            Bits::ShiftRight => lhs.bit_shr(span, rhs, span).ok(),
+        },
+    }
+}
+
+/// Returns the register a single instruction writes its result to, if it writes one at all.
+fn instruction_write_target(instr: &Instruction) -> Option<RegId> {
+    match instr {
+        Instruction::LoadLiteral { dst, .. } => Some(*dst),
+        Instruction::Move { dst, .. } => Some(*dst),
+        Instruction::Clone { dst, .. } => Some(*dst),
+        Instruction::Collect { src_dst } => Some(*src_dst),
+        Instruction::LoadVariable { dst, .. } => Some(*dst),
+        Instruction::LoadEnv { dst, .. } => Some(*dst),
+        Instruction::LoadEnvOpt { dst, .. } => Some(*dst),
+        Instruction::Call { src_dst, .. } => Some(*src_dst),
+        Instruction::ListPush { src_dst, .. } => Some(*src_dst),
+        Instruction::ListSpread { src_dst, .. } => Some(*src_dst),
+        Instruction::RecordInsert { src_dst, .. } => Some(*src_dst),
+        Instruction::RecordSpread { src_dst, .. } => Some(*src_dst),
+        Instruction::Not { src_dst } => Some(*src_dst),
+        Instruction::BinaryOp { lhs_dst, .. } => Some(*lhs_dst),
+        Instruction::FollowCellPath { src_dst, .. } => Some(*src_dst),
+        Instruction::CloneCellPath { dst, .. } => Some(*dst),
+        Instruction::UpsertCellPath { src_dst, .. } => Some(*src_dst),
+        Instruction::Iterate { dst, .. } => Some(*dst),
+        _ => None,
+    }
+}
+
+/// Lowers `and`/`or` `BinaryOp`s into branching IR so the right-hand operand is only evaluated
+/// when its value can actually affect the result, instead of `binary_op`'s eager
+/// `collect_reg`-both-sides evaluation.
+///
+/// This only rewrites the common case where the right-hand operand is produced by a single
+/// instruction immediately preceding the `BinaryOp` (e.g. once both `$a` and `$b` are already
+/// loaded into registers for `$a and $b`). Short-circuiting a right side that's a
+/// multi-instruction subexpression means moving that whole subexpression after the branch, which
+/// has to happen during AST-to-IR lowering rather than as a peephole over an already-built
+/// `IrBlock` - that lowering code doesn't live in this crate, so this pass is deliberately scoped
+/// to what it can rewrite safely after the fact.
+pub(crate) fn lower_short_circuit_boolean_ops(ir_block: &mut IrBlock) {
+    let old_instructions = std::mem::take(&mut ir_block.instructions);
+    let old_spans = std::mem::take(&mut ir_block.spans);
+    let len = old_instructions.len();
+
+    let mut new_instructions = Vec::with_capacity(len);
+    let mut new_spans = Vec::with_capacity(len);
+    let mut is_synthetic = Vec::with_capacity(len);
+    let mut old_to_new = vec![0usize; len];
+    let mut next_free_reg: u32 = ir_block.register_count as u32;
+
+    let eligible_site = |producer_pc: usize| -> Option<(RegId, Boolean, RegId)> {
+        let site_pc = producer_pc + 1;
+        if site_pc >= len {
+            return None;
+        }
+        let Instruction::BinaryOp {
+            lhs_dst,
+            op: Operator::Boolean(boolean @ (Boolean::And | Boolean::Or)),
+            rhs,
+        } = &old_instructions[site_pc]
+        else {
+            return None;
+        };
+        if instruction_write_target(&old_instructions[producer_pc]) == Some(*rhs) {
+            Some((*lhs_dst, *boolean, *rhs))
+        } else {
+            None
+        }
+    };
+
+    let mut pc = 0;
+    while pc < len {
+        if let Some((lhs_dst, boolean, rhs)) = eligible_site(pc) {
+            let site_pc = pc + 1;
+            let span = old_spans[site_pc];
+            let test_reg = RegId(next_free_reg);
+            next_free_reg += 1;
+            let start = new_instructions.len();
+
+            let mut push = |instr: Instruction, span: Span, synthetic: bool| {
+                new_instructions.push(instr);
+                new_spans.push(span);
+                is_synthetic.push(synthetic);
+            };
+
+            push(
+                Instruction::Clone {
+                    dst: test_reg,
+                    src: lhs_dst,
+                },
+                span,
+                true,
+            );
+            match boolean {
+                Boolean::And => {
+                    push(
+                        Instruction::BranchIf {
+                            cond: test_reg,
+                            index: start + 3,
+                        },
+                        span,
+                        true,
+                    );
+                    push(
+                        Instruction::Jump { index: start + 5 },
+                        span,
+                        true,
+                    );
+                    push(old_instructions[pc].clone(), old_spans[pc], false);
+                    push(
+                        Instruction::Move {
+                            dst: lhs_dst,
+                            src: rhs,
+                        },
+                        span,
+                        true,
+                    );
+                }
+                Boolean::Or => {
+                    push(
+                        Instruction::BranchIf {
+                            cond: test_reg,
+                            index: start + 4,
+                        },
+                        span,
+                        true,
+                    );
+                    push(old_instructions[pc].clone(), old_spans[pc], false);
+                    push(
+                        Instruction::Move {
+                            dst: lhs_dst,
+                            src: rhs,
+                        },
+                        span,
+                        true,
+                    );
+                }
+                Boolean::Xor => unreachable!("eligible_site only matches And/Or"),
+            }
+
+            old_to_new[pc] = start;
+            old_to_new[site_pc] = start;
+            pc += 2;
+        } else {
+            old_to_new[pc] = new_instructions.len();
+            new_instructions.push(old_instructions[pc].clone());
+            new_spans.push(old_spans[pc]);
+            is_synthetic.push(false);
+            pc += 1;
+        }
+    }
+
+    for (i, instr) in new_instructions.iter_mut().enumerate() {
+        if is_synthetic[i] {
+            continue;
+        }
+        match instr {
+            Instruction::Jump { index } => *index = old_to_new[*index],
+            Instruction::BranchIf { index, .. } => *index = old_to_new[*index],
+            Instruction::Iterate { end_index, .. } => *end_index = old_to_new[*end_index],
+            _ => {}
+        }
+    }
+
+    ir_block.instructions = new_instructions;
+    ir_block.spans = new_spans;
+    ir_block.register_count = next_free_reg as _;
+}
+
+/// The registers an instruction reads from, and the single register it writes to, if any. A
+/// register that's both read and written (e.g. `BinaryOp`'s `lhs_dst`) appears in both.
+fn instruction_reads_writes(instr: &Instruction) -> (Vec<RegId>, Option<RegId>) {
+    match instr {
+        Instruction::LoadLiteral { dst, .. } => (vec![], Some(*dst)),
+        Instruction::Move { dst, src } => (vec![*src], Some(*dst)),
+        Instruction::Clone { dst, src } => (vec![*src], Some(*dst)),
+        Instruction::Collect { src_dst } => (vec![*src_dst], Some(*src_dst)),
+        Instruction::Drop { src } => (vec![*src], None),
+        Instruction::Drain { src } => (vec![*src], None),
+        Instruction::LoadVariable { dst, .. } => (vec![], Some(*dst)),
+        Instruction::StoreVariable { src, .. } => (vec![*src], None),
+        Instruction::LoadEnv { dst, .. } => (vec![], Some(*dst)),
+        Instruction::LoadEnvOpt { dst, .. } => (vec![], Some(*dst)),
+        Instruction::StoreEnv { src, .. } => (vec![*src], None),
+        Instruction::PushPositional { src } => (vec![*src], None),
+        Instruction::AppendRest { src } => (vec![*src], None),
+        Instruction::PushFlag { .. } => (vec![], None),
+        Instruction::PushNamed { src, .. } => (vec![*src], None),
+        Instruction::RedirectOut { mode } | Instruction::RedirectErr { mode } => {
+            if let RedirectMode::File { path, .. } | RedirectMode::Tee { path, .. } = mode {
+                (vec![*path], None)
+            } else {
+                (vec![], None)
+            }
+        }
+        Instruction::Call { src_dst, .. } => (vec![*src_dst], Some(*src_dst)),
+        Instruction::ListPush { src_dst, item } => (vec![*src_dst, *item], Some(*src_dst)),
+        Instruction::ListSpread { src_dst, items } => (vec![*src_dst, *items], Some(*src_dst)),
+        Instruction::RecordInsert { src_dst, key, val } => {
+            (vec![*src_dst, *key, *val], Some(*src_dst))
+        }
+        Instruction::RecordSpread { src_dst, items } => (vec![*src_dst, *items], Some(*src_dst)),
+        Instruction::Not { src_dst } => (vec![*src_dst], Some(*src_dst)),
+        Instruction::BinaryOp { lhs_dst, rhs, .. } => (vec![*lhs_dst, *rhs], Some(*lhs_dst)),
+        Instruction::FollowCellPath { src_dst, path } => (vec![*src_dst, *path], Some(*src_dst)),
+        Instruction::CloneCellPath { dst, src, path } => (vec![*src, *path], Some(*dst)),
+        Instruction::UpsertCellPath {
+            src_dst,
+            path,
+            new_value,
+        } => (vec![*src_dst, *path, *new_value], Some(*src_dst)),
+        Instruction::Jump { .. } => (vec![], None),
+        Instruction::BranchIf { cond, .. } => (vec![*cond], None),
+        Instruction::Iterate { dst, stream, .. } => (vec![*stream], Some(*dst)),
+        Instruction::Return { src } => (vec![*src], None),
+    }
+}
+
+/// Instruction kinds whose only effect is producing a value in a register - safe to delete
+/// outright when that register is never read afterward.
+fn is_side_effect_free_write(instr: &Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::LoadLiteral { .. }
+            | Instruction::Move { .. }
+            | Instruction::Clone { .. }
+            | Instruction::LoadVariable { .. }
+            | Instruction::LoadEnv { .. }
+            | Instruction::LoadEnvOpt { .. }
+            | Instruction::Not { .. }
+            | Instruction::BinaryOp { .. }
+            | Instruction::FollowCellPath { .. }
+            | Instruction::CloneCellPath { .. }
+            | Instruction::RecordInsert { .. }
+            | Instruction::RecordSpread { .. }
+            | Instruction::ListPush { .. }
+            | Instruction::ListSpread { .. }
+            | Instruction::UpsertCellPath { .. }
+    )
+}
+
+/// Deletes instructions whose only effect is writing a register that's never read afterward
+/// (e.g. `LoadLiteral`, `Clone`, `Move`, `BinaryOp`, `RecordInsert`, `FollowCellPath`), using a
+/// backward liveness analysis that merges live sets at `Jump`/`BranchIf`/`Iterate` join points.
+///
+/// `Call`, `Drain`, `StoreEnv`, `StoreVariable`, the redirect instructions, and `Return` are
+/// always kept regardless of liveness, since producing a register value isn't their only effect.
+pub(crate) fn eliminate_dead_stores(ir_block: &mut IrBlock) {
+    let instructions = &ir_block.instructions;
+    let len = instructions.len();
+    let reg_count = ir_block.register_count as usize;
+
+    if len == 0 || reg_count == 0 {
+        return;
+    }
+
+    let LivenessResult { live_out, .. } = compute_liveness(instructions, reg_count);
+
+    let deleted: Vec<bool> = (0..len)
+        .map(|pc| {
+            is_side_effect_free_write(&instructions[pc])
+                && instruction_write_target(&instructions[pc])
+                    .map(|w| !live_out[pc][reg_index(w)])
+                    .unwrap_or(false)
+        })
+        .collect();
+
+    if deleted.iter().any(|&d| d) {
+        compact_marked(&mut ir_block.instructions, &mut ir_block.spans, &deleted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `IrBlock` has no public constructor anywhere in the tree it's defined in, so tests build
+    /// one directly out of the four fields this module actually reads and writes.
+    fn ir_block(instructions: Vec<Instruction>, register_count: u32) -> IrBlock {
+        let spans = vec![Span::test_data(); instructions.len()];
+        IrBlock {
+            instructions,
+            spans,
+            data: Arc::from(Vec::new()),
+            register_count,
+        }
+    }
+
+    #[test]
+    fn optimize_jumps_collapses_jump_chain_and_drops_the_dead_link() {
+        let mut block = ir_block(
+            vec![
+                Instruction::Jump { index: 1 },
+                Instruction::Jump { index: 2 },
+                Instruction::Return { src: RegId(0) },
+            ],
+            1,
+        );
+
+        optimize_jumps(&mut block);
+
+        assert_eq!(block.instructions.len(), 2);
+        assert!(matches!(
+            block.instructions[0],
+            Instruction::Jump { index: 1 }
+        ));
+        assert!(matches!(
+            block.instructions[1],
+            Instruction::Return { src: RegId(0) }
+        ));
+    }
+
+    #[test]
+    fn fold_constants_folds_a_literal_addition_into_a_single_load() {
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Int(1),
+                },
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(2),
+                },
+                Instruction::BinaryOp {
+                    lhs_dst: RegId(0),
+                    op: Operator::Math(Math::Plus),
+                    rhs: RegId(1),
+                },
+                Instruction::Return { src: RegId(0) },
+            ],
+            2,
+        );
+
+        fold_constants(&mut block);
+
+        assert_eq!(block.instructions.len(), 2);
+        assert!(matches!(
+            block.instructions[0],
+            Instruction::LoadLiteral {
+                dst: RegId(0),
+                lit: Literal::Int(3),
+            }
+        ));
+    }
+
+    #[test]
+    fn compact_registers_reuses_a_register_once_its_predecessor_is_dead() {
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Int(1),
+                },
+                Instruction::Drop { src: RegId(0) },
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(2),
+                },
+                Instruction::Return { src: RegId(1) },
+            ],
+            2,
+        );
+
+        compact_registers(&mut block);
+
+        assert_eq!(block.register_count, 1);
+    }
+
+    #[test]
+    fn eliminate_dead_stores_removes_a_write_that_is_never_read() {
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Int(1),
+                },
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(2),
+                },
+                Instruction::Return { src: RegId(0) },
+            ],
+            2,
+        );
+
+        eliminate_dead_stores(&mut block);
+
+        assert_eq!(block.instructions.len(), 2);
+        assert!(matches!(
+            block.instructions[1],
+            Instruction::Return { src: RegId(0) }
+        ));
+    }
+
+    #[test]
+    fn compact_registers_keeps_a_loop_head_register_live_across_a_backward_branch_back_edge() {
+        // A loop compiled with a plain backward `Jump`/`BranchIf` pair, not `Instruction::Iterate`.
+        // `RegId(0)` (the loop condition) is only read once, textually, at pc2 - but pc2 is
+        // reachable again via the pc5 back edge, so it must stay live across the whole loop body
+        // (pc3-pc5) rather than being freed for reuse right after that lone textual use, or a
+        // later iteration's branch test would read a clobbered value.
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Bool(true),
+                }, // pc0: loop condition
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(0),
+                }, // pc1: accumulator init
+                Instruction::BranchIf {
+                    cond: RegId(0),
+                    index: 6,
+                }, // pc2: loop head
+                Instruction::LoadLiteral {
+                    dst: RegId(2),
+                    lit: Literal::Int(1),
+                }, // pc3: per-iteration temp
+                Instruction::BinaryOp {
+                    lhs_dst: RegId(1),
+                    op: Operator::Math(Math::Plus),
+                    rhs: RegId(2),
+                }, // pc4: accumulator += temp
+                Instruction::Jump { index: 2 }, // pc5: back edge to the loop head
+                Instruction::Return { src: RegId(1) }, // pc6
+            ],
+            3,
+        );
+
+        compact_registers(&mut block);
+
+        // RegId(0), RegId(1), and RegId(2) are all simultaneously live at pc3/pc4, so no valid
+        // allocation can compact this down to fewer than 3 registers. Coalescing RegId(0) with
+        // RegId(2) (the bug this guards against) would wrongly report 2.
+        assert_eq!(block.register_count, 3);
+    }
+
+    #[test]
+    fn compact_registers_keeps_a_register_only_ever_written_on_its_own_physical_slot() {
+        // `Iterate`'s `dst` is written every iteration but never read here (the loop's body
+        // ignores the element it's handed), and `Iterate` isn't eliminated by
+        // `eliminate_dead_stores` as a dead store since the instruction itself still drives the
+        // loop. A register with no read anywhere must still land on its own physical register
+        // rather than being left unmapped, which would default it onto physical register 0 and
+        // silently corrupt whatever value already lives there.
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Int(99),
+                }, // pc0: a value that must survive the loop untouched
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(0),
+                }, // pc1: stands in for the stream to iterate
+                Instruction::Iterate {
+                    dst: RegId(2),
+                    stream: RegId(1),
+                    end_index: 4,
+                }, // pc2: loop head; dst is never read anywhere
+                Instruction::Jump { index: 2 }, // pc3: back edge (body falls through then repeats)
+                Instruction::Return { src: RegId(0) }, // pc4
+            ],
+            3,
+        );
+
+        compact_registers(&mut block);
+
+        // RegId(0) stays live across the whole loop (read only at the very end), so RegId(2)
+        // (write-only) must not be coalesced onto the same physical slot as RegId(0): if it were,
+        // the instruction remapped from pc0's `LoadLiteral` would share a register with the
+        // instruction remapped from pc2's `Iterate`.
+        let load_literal_dst = match &block.instructions[0] {
+            Instruction::LoadLiteral { dst, .. } => *dst,
+            _ => panic!("unexpected instruction shape after compaction"),
+        };
+        let iterate_dst = match &block.instructions[2] {
+            Instruction::Iterate { dst, .. } => *dst,
+            _ => panic!("unexpected instruction shape after compaction"),
+        };
+        assert_ne!(load_literal_dst, iterate_dst);
+    }
+
+    #[test]
+    fn eliminate_dead_stores_keeps_a_loop_head_register_read_through_a_backward_branch_back_edge() {
+        // Same loop shape as above: `RegId(0)` has no textual use after pc2, but is live into the
+        // next iteration via the pc5->pc2 back edge, so it must not be treated as dead and deleted.
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Bool(true),
+                }, // pc0: loop condition
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(0),
+                }, // pc1: accumulator init
+                Instruction::BranchIf {
+                    cond: RegId(0),
+                    index: 6,
+                }, // pc2: loop head
+                Instruction::LoadLiteral {
+                    dst: RegId(2),
+                    lit: Literal::Int(1),
+                }, // pc3: per-iteration temp
+                Instruction::BinaryOp {
+                    lhs_dst: RegId(1),
+                    op: Operator::Math(Math::Plus),
+                    rhs: RegId(2),
+                }, // pc4: accumulator += temp
+                Instruction::Jump { index: 2 }, // pc5: back edge to the loop head
+                Instruction::Return { src: RegId(1) }, // pc6
+            ],
+            3,
         );
-        eval_iterate(ctx, dst, stream, end_index)
+
+        eliminate_dead_stores(&mut block);
+
+        // Nothing here is actually dead - the LoadLiteral at pc0 feeds the BranchIf at pc2 on
+        // every iteration via the back edge, so the instruction count must be unchanged.
+        assert_eq!(block.instructions.len(), 7);
+    }
+
+    #[test]
+    fn lower_short_circuit_boolean_ops_branches_around_the_rhs_producer() {
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Bool(true),
+                },
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Bool(false),
+                },
+                Instruction::BinaryOp {
+                    lhs_dst: RegId(0),
+                    op: Operator::Boolean(Boolean::And),
+                    rhs: RegId(1),
+                },
+                Instruction::Return { src: RegId(0) },
+            ],
+            2,
+        );
+
+        lower_short_circuit_boolean_ops(&mut block);
+
+        // The `and` is gone, replaced by a branch around the RHS producer plus a merge move, so
+        // the RHS is only evaluated when the LHS didn't already decide the result.
+        assert!(!block.instructions.iter().any(|i| matches!(
+            i,
+            Instruction::BinaryOp {
+                op: Operator::Boolean(Boolean::And),
+                ..
+            }
+        )));
+        assert!(block
+            .instructions
+            .iter()
+            .any(|i| matches!(i, Instruction::BranchIf { .. })));
+        assert_eq!(block.register_count, 3);
+    }
+
+    #[test]
+    fn optimize_ir_block_runs_every_pass_without_panicking() {
+        let mut block = ir_block(
+            vec![
+                Instruction::LoadLiteral {
+                    dst: RegId(0),
+                    lit: Literal::Int(1),
+                },
+                Instruction::LoadLiteral {
+                    dst: RegId(1),
+                    lit: Literal::Int(2),
+                },
+                Instruction::BinaryOp {
+                    lhs_dst: RegId(0),
+                    op: Operator::Math(Math::Plus),
+                    rhs: RegId(1),
+                },
+                Instruction::Return { src: RegId(0) },
+            ],
+            2,
+        );
+
+        optimize_ir_block(&mut block);
+
+        assert!(matches!(
+            block.instructions.last(),
+            Some(Instruction::Return { .. })
+        ));
     }
 }
@@ -22,11 +22,16 @@ use crossterm::{
 };
 use lscolors::LsColors;
 use nu_color_config::{lookup_ansi_color_style, StyleComputer};
+use regex::Regex;
 use nu_protocol::{
     engine::{EngineState, Stack},
     Record, Value,
 };
-use ratatui::{backend::CrosstermBackend, layout::Rect, widgets::Block};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
 
 use crate::{
     nu_common::{CtrlC, NuColor, NuConfig, NuSpan, NuStyle},
@@ -49,22 +54,61 @@ pub type Frame<'a> = ratatui::Frame<'a>;
 pub type Terminal = ratatui::Terminal<CrosstermBackend<Stdout>>;
 pub type ConfigMap = HashMap<String, Value>;
 
-#[derive(Debug, Clone)]
 pub struct Pager<'a> {
     config: PagerConfig<'a>,
     message: Option<String>,
     cmd_buf: CommandBuf,
     search_buf: SearchBuf,
+    filter_buf: FilterBuf,
+    compositor: Compositor,
 }
 
 #[derive(Debug, Clone, Default)]
 struct SearchBuf {
     buf_cmd: String,
     buf_cmd_input: String,
-    search_results: Vec<usize>,
+    search_results: Vec<SearchMatch>,
     search_index: usize,
     is_reversed: bool,
     is_search_input: bool,
+    /// How `buf_cmd_input` is interpreted: literal substring, regex, or fuzzy subsequence.
+    /// Seeded from [`PagerConfig::search_mode`] when the pager is created and whenever
+    /// `set_config` changes `search_mode`, and cycled for the rest of the session with `Ctrl-r`.
+    mode: SearchMode,
+}
+
+/// The pattern engine used for `/`-style search, settable via the `search_mode` path in
+/// [`Pager::set_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SearchMode {
+    #[default]
+    Literal,
+    Regex,
+    /// fzf-style subsequence matching; see [`fuzzy_match`].
+    Fuzzy,
+}
+
+impl SearchMode {
+    /// Cycle to the next mode, in the order `Ctrl-r` steps through.
+    fn next(self) -> SearchMode {
+        match self {
+            SearchMode::Literal => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Fuzzy,
+            SearchMode::Fuzzy => SearchMode::Literal,
+        }
+    }
+}
+
+/// A row that matched the active search pattern, with every match span on that row so the
+/// render path can highlight each one rather than just letting the view jump to the row.
+/// Ranges are char offsets into the row's ANSI-stripped rendered text, as returned by
+/// `View::collect_data`. `score` is only meaningful for [`SearchMode::Fuzzy`] matches, where it
+/// drives the sort order; literal/regex matches all carry a score of `0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SearchMatch {
+    row: usize,
+    ranges: Vec<(usize, usize)>,
+    score: i64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -76,6 +120,133 @@ struct CommandBuf {
     cmd_history_allow: bool,
     cmd_history_pos: usize,
     cmd_exec_info: Option<String>,
+    /// Digits accumulated for a vi-style count prefix, e.g. the "5" in "5j".
+    vi_count: String,
+    /// Set after a bare `g`, waiting for a second `g` to complete the `gg` motion.
+    vi_pending_g: bool,
+    /// Our best-effort tracking of the row the last vi motion landed on, since `View` doesn't
+    /// expose its own cursor position to us.
+    vi_row: usize,
+    /// Chords typed so far toward a multi-chord user keybinding, e.g. the first `g` of `"g g"`.
+    key_seq: Vec<KeyChord>,
+    /// Candidates for the current token in `buf_cmd2`, shown as a popup while typing. Empty
+    /// unless `Tab` was just pressed (or the popup is still open from a previous press).
+    completions: Vec<CompletionItem>,
+    completion_index: usize,
+    /// Whether a `Ctrl-r` reverse-incremental history search is in progress.
+    is_history_search: bool,
+    /// The query typed so far while `is_history_search` is set.
+    history_search_query: String,
+    /// Indices into `cmd_history` matching `history_search_query`, most-recent-first.
+    history_search_matches: Vec<usize>,
+    /// Position into `history_search_matches` currently previewed in `buf_cmd2`.
+    history_search_pos: usize,
+    /// `buf_cmd2` as it was before entering history search, restored on `Esc`.
+    pre_search_buf: String,
+}
+
+/// A single entry in the `:`-command completion popup, modeled on an IDE completion item so we
+/// can later show per-command argument hints alongside the name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompletionItem {
+    label: String,
+    detail: Option<String>,
+}
+
+/// Known pager command names and a one-line description, used to drive completion.
+///
+/// `CommandRegistry` only exposes lookup by exact name (`find`), not enumeration, so this list
+/// is maintained by hand alongside the commands `pager_run_command` actually dispatches to.
+const KNOWN_COMMANDS: &[(&str, &str)] = &[
+    ("table", "Render the current value as a table"),
+    ("filter", "Pipe visible rows through an external program"),
+    ("nu", "Open a value in a new nu-explore view"),
+    ("try", "Run commands against the current value without committing"),
+    ("help", "Show available pager commands"),
+    ("quit", "Exit the pager"),
+];
+
+/// How many most-recent `:`-command history entries [`push_cmd_history`] keeps in memory. The
+/// history file on disk is only ever appended to and is not trimmed to this length.
+const MAX_CMD_HISTORY_LEN: usize = 1000;
+
+/// Indices into `history` whose entry fuzzy/substring-matches `query`, most-recent-first (so
+/// repeated `Ctrl-r` walks toward older entries and `Ctrl-s` walks back toward newer ones).
+fn history_search_matches(history: &[String], query: &str) -> Vec<usize> {
+    (0..history.len())
+        .rev()
+        .filter(|&i| query.is_empty() || fuzzy_match(query, &history[i]).is_some())
+        .collect()
+}
+
+/// Load `:`-command history from disk, one entry per line, oldest first.
+fn load_cmd_history(path: &std::path::Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|contents| contents.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Append `entry` to the history file at `path`, one entry per line.
+fn append_cmd_history(path: &std::path::Path, entry: &str) {
+    use std::io::Write;
+
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path);
+    if let Ok(mut file) = file {
+        let _ = writeln!(file, "{entry}");
+    }
+}
+
+/// Push `entry` onto `history`, de-duplicating an immediately-repeated entry and capping the
+/// list at [`MAX_CMD_HISTORY_LEN`].
+fn push_cmd_history(history: &mut Vec<String>, entry: String) {
+    if history.last() == Some(&entry) {
+        return;
+    }
+
+    history.push(entry);
+    if history.len() > MAX_CMD_HISTORY_LEN {
+        let overflow = history.len() - MAX_CMD_HISTORY_LEN;
+        history.drain(..overflow);
+    }
+}
+
+/// Fuzzy-rank [`KNOWN_COMMANDS`] against `token`, returning the matches in best-first order.
+/// An empty `token` returns every known command, alphabetically.
+fn complete_command_name(token: &str) -> Vec<CompletionItem> {
+    if token.is_empty() {
+        let mut all: Vec<CompletionItem> = KNOWN_COMMANDS
+            .iter()
+            .map(|(label, detail)| CompletionItem {
+                label: label.to_string(),
+                detail: Some(detail.to_string()),
+            })
+            .collect();
+        all.sort_by(|a, b| a.label.cmp(&b.label));
+        return all;
+    }
+
+    let mut scored: Vec<(i64, &(&str, &str))> = KNOWN_COMMANDS
+        .iter()
+        .filter_map(|entry| fuzzy_match(token, entry.0).map(|(score, _)| (score, entry)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1 .0.cmp(b.1 .0)));
+
+    scored
+        .into_iter()
+        .map(|(_, (label, detail))| CompletionItem {
+            label: label.to_string(),
+            detail: Some(detail.to_string()),
+        })
+        .collect()
+}
+
+/// Tracks an active `:filter PROGRAM [ARGS...]` pipe and what the view looked like before it was
+/// applied, so that `Esc` can restore the original rows instead of leaving the filtered view in
+/// place the way replacing a view via `Command::View` does.
+#[derive(Debug, Clone, Default)]
+struct FilterBuf {
+    command: Option<String>,
+    original_value: Option<Value>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -88,15 +259,33 @@ pub struct StyleConfig {
     pub status_bar_text: NuStyle,
     pub cmd_bar_text: NuStyle,
     pub cmd_bar_background: NuStyle,
-    pub highlight: NuStyle,
+    /// Style painted over each span returned by `search_pattern`, i.e. the `/`-search hits.
+    pub search_highlight: NuStyle,
 }
 
 impl<'a> Pager<'a> {
     pub fn new(config: PagerConfig<'a>) -> Self {
+        let search_buf = SearchBuf {
+            mode: config.search_mode,
+            ..Default::default()
+        };
+
+        let cmd_history = match &config.history_path {
+            Some(path) => load_cmd_history(path),
+            None => Vec::new(),
+        };
+        let cmd_buf = CommandBuf {
+            cmd_history_pos: cmd_history.len(),
+            cmd_history,
+            ..Default::default()
+        };
+
         Self {
             config,
-            cmd_buf: CommandBuf::default(),
-            search_buf: SearchBuf::default(),
+            cmd_buf,
+            search_buf,
+            filter_buf: FilterBuf::default(),
+            compositor: Compositor::default(),
             message: None,
         }
     }
@@ -105,6 +294,27 @@ impl<'a> Pager<'a> {
         self.message = Some(text.into());
     }
 
+    /// Push a layer on top of the compositor stack, e.g. to show a help screen or popup over the
+    /// current view without disturbing it.
+    ///
+    /// Nothing in this module calls this yet: the `:`-command completion popup
+    /// (`render_completion_popup`) still draws itself directly off `cmd_buf.completions` instead
+    /// of going through a `Layer`, because doing so for real needs a `View` impl for the popup,
+    /// and `View` is a trait this crate doesn't carry a definition of in this checkout. Left in
+    /// place for whichever popup (a help screen, a command palette) ends up being the first to
+    /// need it.
+    #[allow(dead_code)]
+    pub(crate) fn push_layer(&mut self, view: Box<dyn View>, kind: LayerKind) {
+        self.compositor.push(view, kind);
+    }
+
+    /// Pop the topmost compositor layer, if any. See [`Pager::push_layer`] for why nothing calls
+    /// this yet either.
+    #[allow(dead_code)]
+    pub(crate) fn pop_layer(&mut self) -> Option<Box<dyn View>> {
+        self.compositor.pop().map(|layer| layer.view)
+    }
+
     pub fn set_config(&mut self, path: &[String], value: Value) -> bool {
         let path = path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
 
@@ -117,11 +327,47 @@ impl<'a> Pager<'a> {
             ["command_bar_background"] => {
                 value_as_style(&mut self.config.style.cmd_bar_background, &value)
             }
-            ["highlight"] => value_as_style(&mut self.config.style.highlight, &value),
+            ["search_highlight"] => {
+                value_as_style(&mut self.config.style.search_highlight, &value)
+            }
             ["status", "info"] => value_as_style(&mut self.config.style.status_info, &value),
             ["status", "success"] => value_as_style(&mut self.config.style.status_success, &value),
             ["status", "warn"] => value_as_style(&mut self.config.style.status_warn, &value),
             ["status", "error"] => value_as_style(&mut self.config.style.status_error, &value),
+            ["keybindings"] => {
+                self.config.keybindings = parse_keybindings(&value);
+                true
+            }
+            ["search_mode"] => match value.coerce_str() {
+                Ok(mode) if mode.as_ref() == "regex" => {
+                    self.config.search_mode = SearchMode::Regex;
+                    self.search_buf.mode = SearchMode::Regex;
+                    true
+                }
+                Ok(mode) if mode.as_ref() == "literal" => {
+                    self.config.search_mode = SearchMode::Literal;
+                    self.search_buf.mode = SearchMode::Literal;
+                    true
+                }
+                Ok(mode) if mode.as_ref() == "fuzzy" => {
+                    self.config.search_mode = SearchMode::Fuzzy;
+                    self.search_buf.mode = SearchMode::Fuzzy;
+                    true
+                }
+                _ => false,
+            },
+            ["history_path"] => match value.coerce_str() {
+                Ok(path) => {
+                    let path = std::path::PathBuf::from(path.as_ref());
+                    if self.cmd_buf.cmd_history.is_empty() {
+                        self.cmd_buf.cmd_history = load_cmd_history(&path);
+                        self.cmd_buf.cmd_history_pos = self.cmd_buf.cmd_history.len();
+                    }
+                    self.config.history_path = Some(path);
+                    true
+                }
+                Err(_) => false,
+            },
             path => set_config(&mut self.config.config, path, value),
         }
     }
@@ -164,6 +410,10 @@ pub struct PagerConfig<'a> {
     pub style: StyleConfig,
     pub peek_value: bool,
     pub reverse: bool,
+    keybindings: KeyBindings,
+    search_mode: SearchMode,
+    /// Where `:`-command history is persisted. `None` keeps history session-only.
+    history_path: Option<std::path::PathBuf>,
 }
 
 impl<'a> PagerConfig<'a> {
@@ -181,8 +431,111 @@ impl<'a> PagerConfig<'a> {
             peek_value: false,
             reverse: false,
             style: StyleConfig::default(),
+            keybindings: KeyBindings::default(),
+            search_mode: SearchMode::default(),
+            history_path: None,
+        }
+    }
+}
+
+/// A key chord: a set of modifiers plus a [`KeyCode`], e.g. `ctrl-c` or `g`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct KeyChord {
+    modifiers: KeyModifiers,
+    code: KeyCode,
+}
+
+/// The action a [`KeyBindings`] entry runs once its whole chord sequence has been typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KeyAction {
+    Exit,
+    SearchForward,
+    SearchReverse,
+    EnterCommand,
+    /// Run this as a pager command, as if typed after `:`.
+    Command(String),
+    /// Parsed but not a recognized action name; reported as an error when triggered.
+    Unknown(String),
+}
+
+/// User-configurable keybindings, parsed from the `keybindings` path in [`Pager::set_config`]:
+/// a record mapping chord-sequence strings (e.g. `"ctrl-c"`, `"g g"`, `"?"`) to action names.
+#[derive(Debug, Clone, Default)]
+struct KeyBindings {
+    bindings: Vec<(Vec<KeyChord>, KeyAction)>,
+}
+
+fn parse_key_chord(token: &str) -> result::Result<KeyChord, String> {
+    let mut parts: Vec<&str> = token.split('-').collect();
+    let code_str = parts
+        .pop()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("empty key chord {token:?}"))?;
+
+    let mut modifiers = KeyModifiers::NONE;
+    for part in parts {
+        modifiers |= match part {
+            "ctrl" => KeyModifiers::CONTROL,
+            "shift" => KeyModifiers::SHIFT,
+            "alt" => KeyModifiers::ALT,
+            other => return Err(format!("unknown key modifier {other:?} in {token:?}")),
+        };
+    }
+
+    let code = match code_str {
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        s if s.chars().count() == 1 => KeyCode::Char(s.chars().next().expect("checked above")),
+        other => return Err(format!("unknown key {other:?} in {token:?}")),
+    };
+
+    Ok(KeyChord { modifiers, code })
+}
+
+fn parse_key_sequence(spec: &str) -> result::Result<Vec<KeyChord>, String> {
+    spec.split_whitespace().map(parse_key_chord).collect()
+}
+
+fn parse_key_action(name: &str) -> KeyAction {
+    if let Some(cmd) = name.strip_prefix(':') {
+        return KeyAction::Command(cmd.to_string());
+    }
+
+    match name {
+        "exit" => KeyAction::Exit,
+        "search_forward" => KeyAction::SearchForward,
+        "search_reverse" => KeyAction::SearchReverse,
+        "enter_command" => KeyAction::EnterCommand,
+        other => KeyAction::Unknown(other.to_string()),
+    }
+}
+
+fn parse_keybindings(value: &Value) -> KeyBindings {
+    let mut bindings = KeyBindings::default();
+
+    let Value::Record { val: record, .. } = value else {
+        return bindings;
+    };
+
+    for (spec, action) in record.iter() {
+        let Ok(action) = action.coerce_str() else {
+            continue;
+        };
+
+        if let Ok(chords) = parse_key_sequence(spec) {
+            if !chords.is_empty() {
+                bindings.bindings.push((chords, parse_key_action(&action)));
+            }
         }
     }
+
+    bindings
 }
 
 fn run_pager(
@@ -266,6 +619,10 @@ fn render_ui(
             info,
             &mut pager.search_buf,
             &mut pager.cmd_buf,
+            &pager.filter_buf,
+            &mut pager.compositor,
+            &pager.config.keybindings,
+            pager.config.history_path.as_deref(),
             view_stack.view.as_mut().map(|p| &mut p.view),
         );
 
@@ -399,12 +756,34 @@ fn draw_frame(
         page.view.draw(f, available_area, cfg, layout);
     }
 
+    draw_compositor(f, available_area, pager, layout);
+
     draw_info(f, pager, info);
 
-    highlight_search_results(f, pager, layout, pager.config.style.highlight);
+    highlight_search_results(f, pager, layout, pager.config.style.search_highlight);
     set_cursor_cmd_bar(f, area, pager);
 }
 
+/// Draw the compositor's layers bottom-to-top over `area`, without disturbing `view`.
+fn draw_compositor(f: &mut Frame, area: Rect, pager: &mut Pager<'_>, layout: &mut Layout) {
+    let mut compositor = std::mem::take(&mut pager.compositor);
+
+    for layer in &mut compositor.layers {
+        let layer_area = match layer.kind {
+            LayerKind::Modal => area,
+            LayerKind::Popup {
+                width_percent,
+                height_percent,
+            } => centered_rect(area, width_percent, height_percent),
+        };
+
+        let cfg = create_view_config(pager);
+        layer.view.draw(f, layer_area, cfg, layout);
+    }
+
+    pager.compositor = compositor;
+}
+
 fn draw_info(f: &mut Frame, pager: &mut Pager<'_>, info: ViewInfo) {
     let area = f.size();
 
@@ -434,6 +813,12 @@ fn pager_run_command(
     commands: &CommandRegistry,
     args: String,
 ) -> result::Result<CmdResult, String> {
+    let mut words = args.splitn(2, ' ');
+    if words.next() == Some("filter") {
+        let rest = words.next().unwrap_or("").trim();
+        return run_filter_command(engine_state, stack, pager, view_stack, commands, rest);
+    }
+
     let command = commands.find(&args);
     match command {
         Some(Ok(command)) => {
@@ -495,6 +880,155 @@ fn run_command(
     }
 }
 
+/// Handle `:filter PROGRAM [ARGS...]` and `:filter --clear`.
+///
+/// Pipes the rows currently backing the view through an external program and rebuilds the view
+/// from its stdout, or (for `--clear`) restores the view that was active before the filter ran.
+fn run_filter_command(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pager: &mut Pager,
+    view_stack: &mut ViewStack,
+    commands: &CommandRegistry,
+    filter_args: &str,
+) -> result::Result<CmdResult, String> {
+    if filter_args == "--clear" {
+        return clear_filter(engine_state, stack, pager, view_stack, commands);
+    }
+
+    let mut parts = filter_args.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| String::from("Error: :filter requires a program to run, e.g. :filter grep ERROR"))?;
+    let program_args = parts.collect::<Vec<_>>();
+
+    let rows = match view_stack.view.as_mut() {
+        Some(page) => page
+            .view
+            .collect_data()
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect::<Vec<_>>(),
+        None => return Err(String::from("Error: :filter has no view to filter")),
+    };
+
+    if pager.filter_buf.original_value.is_none() {
+        pager.filter_buf.original_value =
+            view_stack.view.as_mut().and_then(|p| p.view.exit());
+    }
+
+    let mut child = std::process::Command::new(program)
+        .args(&program_args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Error: failed to spawn {program:?}: {err}"))?;
+
+    // Writing all of stdin before reading any of stdout/stderr deadlocks once the filtered rows
+    // are large enough to fill either pipe's OS buffer: the child blocks writing output we
+    // haven't started draining, while we block writing input it hasn't started draining. Doing
+    // the write on its own thread lets it run concurrently with `wait_with_output`'s read loop
+    // below instead.
+    let mut stdin = child
+        .stdin
+        .take()
+        .expect("stdin was requested to be piped");
+    let input = rows.join("\n");
+    let stdin_writer = std::thread::spawn(move || {
+        use std::io::Write;
+        stdin.write_all(input.as_bytes())
+    });
+
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Error: {program:?} failed: {err}"))?;
+
+    stdin_writer
+        .join()
+        .expect("stdin writer thread panicked")
+        .map_err(|err| format!("Error: failed to write to {program:?}: {err}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Error: {program:?} exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let span = NuSpan::unknown();
+    let rows = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| Value::string(line, span))
+        .collect::<Vec<_>>();
+    let filtered_value = Value::list(rows, span);
+
+    rebuild_view_from_value(engine_state, stack, pager, view_stack, commands, filtered_value)?;
+    pager.filter_buf.command = Some(filter_args.to_string());
+
+    Ok(CmdResult::new(false, true, String::from("filter")))
+}
+
+fn clear_filter(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pager: &mut Pager,
+    view_stack: &mut ViewStack,
+    commands: &CommandRegistry,
+) -> result::Result<CmdResult, String> {
+    let value = match pager.filter_buf.original_value.take() {
+        Some(value) => value,
+        None => return Ok(CmdResult::new(false, false, String::new())),
+    };
+
+    rebuild_view_from_value(engine_state, stack, pager, view_stack, commands, value)?;
+    pager.filter_buf.command = None;
+
+    Ok(CmdResult::new(false, true, String::new()))
+}
+
+/// Spawn the default `table` view over `value` and make it the active page, pushing the
+/// previous page onto the stack the same way [`run_command`] does for `Command::View`.
+fn rebuild_view_from_value(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pager: &mut Pager,
+    view_stack: &mut ViewStack,
+    commands: &CommandRegistry,
+    value: Value,
+) -> result::Result<(), String> {
+    let command = match commands.find("table") {
+        Some(Ok(command)) => command,
+        Some(Err(err)) => {
+            return Err(format!("Error: couldn't rebuild the view: {err}"));
+        }
+        None => return Err(String::from("Error: no `table` view is registered")),
+    };
+
+    match command {
+        Command::View { mut cmd, is_light } => {
+            let mut new_view = cmd
+                .spawn(engine_state, stack, Some(value))
+                .map_err(|err| format!("Error: couldn't rebuild the view: {err}"))?;
+
+            if let Some(view) = view_stack.view.take() {
+                if !view.is_light {
+                    view_stack.stack.push(view);
+                }
+            }
+
+            update_view_setup(&mut new_view, &pager.config);
+            view_stack.view = Some(Page::raw(new_view, is_light));
+
+            Ok(())
+        }
+        Command::Reactive(_) => Err(String::from(
+            "Error: `table` resolved to a reactive command, not a view",
+        )),
+    }
+}
+
 fn update_view_stack_setup(view_stack: &mut ViewStack, cfg: &PagerConfig<'_>) {
     if let Some(page) = view_stack.view.as_mut() {
         update_view_setup(&mut page.view, cfg);
@@ -588,16 +1122,33 @@ fn render_cmd_bar(
         return;
     }
 
+    if pager.cmd_buf.is_cmd_input && pager.cmd_buf.is_history_search {
+        render_cmd_bar_history_search(f, area, pager, theme);
+        return;
+    }
+
     if pager.cmd_buf.is_cmd_input {
+        render_completion_popup(f, area, pager, theme);
         render_cmd_bar_cmd(f, area, pager, theme);
         return;
     }
 
     if pager.search_buf.is_search_input || !pager.search_buf.buf_cmd_input.is_empty() {
         render_cmd_bar_search(f, area, pager, theme);
+        return;
+    }
+
+    if let Some(filter) = &pager.filter_buf.command {
+        render_cmd_bar_filter(f, area, filter, theme);
     }
 }
 
+fn render_cmd_bar_filter(f: &mut Frame, area: Rect, filter: &str, theme: &StyleConfig) {
+    let text = format!(":filter {filter}");
+    let bar = CommandBar::new(&text, "Esc to clear", theme.cmd_bar_text, theme.cmd_bar_background);
+    f.render_widget(bar, area);
+}
+
 fn render_cmd_bar_search(f: &mut Frame, area: Rect, pager: &Pager<'_>, theme: &StyleConfig) {
     if pager.search_buf.search_results.is_empty() && !pager.search_buf.is_search_input {
         let message = format!("Pattern not found: {}", pager.search_buf.buf_cmd_input);
@@ -652,22 +1203,107 @@ fn render_cmd_bar_cmd(f: &mut Frame, area: Rect, pager: &Pager, theme: &StyleCon
     f.render_widget(bar, area);
 }
 
+/// Draw the `(reverse-i-search)'query': preview` prompt while `Ctrl-r` history search is active.
+fn render_cmd_bar_history_search(f: &mut Frame, area: Rect, pager: &Pager, theme: &StyleConfig) {
+    let cmd = &pager.cmd_buf;
+    let text = format!(
+        "(reverse-i-search)'{}': {}",
+        cmd.history_search_query, cmd.buf_cmd2
+    );
+    let info = if cmd.history_search_matches.is_empty() {
+        String::from("0/0")
+    } else {
+        format!(
+            "{}/{}",
+            cmd.history_search_pos + 1,
+            cmd.history_search_matches.len()
+        )
+    };
+
+    let bar = CommandBar::new(&text, &info, theme.cmd_bar_text, theme.cmd_bar_background);
+    f.render_widget(bar, area);
+}
+
+/// Draw the `:`-command completion list as a popup anchored just above the command bar.
+fn render_completion_popup(f: &mut Frame, cmd_bar_area: Rect, pager: &Pager, theme: &StyleConfig) {
+    let completions = &pager.cmd_buf.completions;
+    if completions.is_empty() {
+        return;
+    }
+
+    let visible = completions.len().min(6);
+    let height = visible as u16 + 2;
+    let area = Rect::new(
+        cmd_bar_area.x,
+        cmd_bar_area.y.saturating_sub(height),
+        cmd_bar_area.width,
+        height,
+    );
+
+    let items: Vec<ListItem> = completions
+        .iter()
+        .take(visible)
+        .enumerate()
+        .map(|(i, item)| {
+            let text = match &item.detail {
+                Some(detail) => format!("{} — {detail}", item.label),
+                None => item.label.clone(),
+            };
+
+            let style = if i == pager.cmd_buf.completion_index {
+                nu_style_to_tui(theme.cmd_bar_background)
+            } else {
+                nu_style_to_tui(theme.cmd_bar_text)
+            };
+
+            ListItem::new(text).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
 fn highlight_search_results(f: &mut Frame, pager: &Pager, layout: &Layout, style: NuStyle) {
-    if pager.search_buf.search_results.is_empty() {
+    if pager.search_buf.search_results.is_empty() || pager.search_buf.buf_cmd_input.is_empty() {
         return;
     }
 
     let highlight_block = Block::default().style(nu_style_to_tui(style));
+    let pat = &pager.search_buf.buf_cmd_input;
+
+    let ranges_for = |text: &str| -> Vec<(usize, usize)> {
+        match pager.search_buf.mode {
+            SearchMode::Fuzzy => fuzzy_match(pat, text)
+                .map(|(_, positions)| positions.into_iter().map(|p| (p, p + 1)).collect())
+                .unwrap_or_default(),
+            mode => {
+                let regex = build_search_regex(pat, mode == SearchMode::Regex);
+                regex
+                    .find_iter(text)
+                    .map(|m| {
+                        (
+                            covert_bytes_to_chars(text, m.start()),
+                            covert_bytes_to_chars(text, m.end()),
+                        )
+                    })
+                    .collect()
+            }
+        }
+    };
 
     for e in &layout.data {
         let text = ansi_str::AnsiStr::ansi_strip(&e.text);
 
-        if let Some(p) = text.find(&pager.search_buf.buf_cmd_input) {
-            let p = covert_bytes_to_chars(&text, p);
-
-            let w = pager.search_buf.buf_cmd_input.len() as u16;
-            let area = Rect::new(e.area.x + p as u16, e.area.y, w, 1);
+        for (start, end) in ranges_for(&text) {
+            let w = end.saturating_sub(start) as u16;
+            if w == 0 {
+                continue;
+            }
 
+            let area = Rect::new(e.area.x + start as u16, e.area.y, w, 1);
             f.render_widget(highlight_block.clone(), area);
         }
     }
@@ -697,6 +1333,10 @@ fn handle_events<V: View>(
     info: &mut ViewInfo,
     search: &mut SearchBuf,
     command: &mut CommandBuf,
+    filter: &FilterBuf,
+    compositor: &mut Compositor,
+    keybindings: &KeyBindings,
+    history_path: Option<&std::path::Path>,
     mut view: Option<&mut V>,
 ) -> Option<Transition> {
     let key = match events.next() {
@@ -711,6 +1351,10 @@ fn handle_events<V: View>(
         info,
         search,
         command,
+        filter,
+        compositor,
+        keybindings,
+        history_path,
         view.as_deref_mut(),
         key,
     );
@@ -733,6 +1377,10 @@ fn handle_events<V: View>(
             info,
             search,
             command,
+            filter,
+            compositor,
+            keybindings,
+            history_path,
             view.as_deref_mut(),
             key,
         );
@@ -753,17 +1401,35 @@ fn handle_event<V: View>(
     info: &mut ViewInfo,
     search: &mut SearchBuf,
     command: &mut CommandBuf,
+    filter: &FilterBuf,
+    compositor: &mut Compositor,
+    keybindings: &KeyBindings,
+    history_path: Option<&std::path::Path>,
     mut view: Option<&mut V>,
     key: KeyEvent,
 ) -> Option<Transition> {
+    if let Some(transition) = handle_keybinding_event(&key, keybindings, command, search, info) {
+        return transition;
+    }
+
     if handle_exit_key_event(&key) {
         return Some(Transition::Exit);
     }
 
-    if handle_general_key_events1(&key, search, command, view.as_deref_mut()) {
+    if !compositor.is_empty() {
+        if let Some(transition) = compositor.handle_event(engine_state, stack, layout, info, key) {
+            return transition;
+        }
+    }
+
+    if handle_general_key_events1(&key, search, command, history_path, view.as_deref_mut()) {
         return None;
     }
 
+    if let Some(transition) = handle_vi_key_event(&key, command, view.as_deref_mut()) {
+        return transition;
+    }
+
     if let Some(view) = &mut view {
         let t = view.handle_input(engine_state, stack, layout, info, key);
         match t {
@@ -775,9 +1441,7 @@ fn handle_event<V: View>(
     }
 
     // was not handled so we must check our default controls
-    handle_general_key_events2(&key, search, command, view, info);
-
-    None
+    handle_general_key_events2(&key, search, command, view, info, filter)
 }
 
 fn handle_exit_key_event(key: &KeyEvent) -> bool {
@@ -794,6 +1458,7 @@ fn handle_general_key_events1<V>(
     key: &KeyEvent,
     search: &mut SearchBuf,
     command: &mut CommandBuf,
+    history_path: Option<&std::path::Path>,
     view: Option<&mut V>,
 ) -> bool
 where
@@ -804,7 +1469,7 @@ where
     }
 
     if command.is_cmd_input {
-        return cmd_input_key_event(command, key);
+        return cmd_input_key_event(command, history_path, key);
     }
 
     false
@@ -816,10 +1481,15 @@ fn handle_general_key_events2<V>(
     command: &mut CommandBuf,
     view: Option<&mut V>,
     info: &mut ViewInfo,
-) where
+    filter: &FilterBuf,
+) -> Option<Transition>
+where
     V: View,
 {
     match key.code {
+        KeyCode::Esc if filter.command.is_some() => {
+            return Some(Transition::Cmd(String::from("filter --clear")));
+        }
         KeyCode::Char('?') => {
             search.buf_cmd_input = String::new();
             search.is_search_input = true;
@@ -853,7 +1523,25 @@ fn handle_general_key_events2<V>(
                     search.search_index += 1;
                 }
 
-                let pos = search.search_results[search.search_index];
+                let pos = search.search_results[search.search_index].row;
+                if let Some(view) = view {
+                    view.show_data(pos);
+                }
+            }
+        }
+        KeyCode::Char('N') => {
+            if !search.search_results.is_empty() {
+                if search.buf_cmd_input.is_empty() {
+                    search.buf_cmd_input = search.buf_cmd.clone();
+                }
+
+                if search.search_index == 0 {
+                    search.search_index = search.search_results.len() - 1;
+                } else {
+                    search.search_index -= 1;
+                }
+
+                let pos = search.search_results[search.search_index].row;
                 if let Some(view) = view {
                     view.show_data(pos);
                 }
@@ -861,6 +1549,287 @@ fn handle_general_key_events2<V>(
         }
         _ => {}
     }
+
+    None
+}
+
+/// Classification used by the vi word motions (`w`/`b`/`e`) and their "long word" variants
+/// (`W`/`B`/`E`, which treat any run of non-whitespace as a single word).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punct,
+}
+
+fn classify_char(c: char, long_word: bool) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if long_word || c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punct
+    }
+}
+
+/// A per-row class, one per row currently backing the view, based on each row's first
+/// non-whitespace character. Rows are the only unit `View` lets us address (via `show_data`), so
+/// vi motions here operate over rows the way real vi operates over characters in a line.
+fn row_classes(view: &mut impl View, long_word: bool) -> Vec<CharClass> {
+    view.collect_data()
+        .into_iter()
+        .map(|(text, _)| {
+            let text = ansi_str::AnsiStr::ansi_strip(&text);
+            match text.trim_start().chars().next() {
+                Some(c) => classify_char(c, long_word),
+                None => CharClass::Whitespace,
+            }
+        })
+        .collect()
+}
+
+fn next_word_boundary(classes: &[CharClass], from: usize) -> usize {
+    let last = classes.len() - 1;
+    let mut i = from;
+    if classes[i] != CharClass::Whitespace {
+        while i < last && classes[i + 1] == classes[i] {
+            i += 1;
+        }
+    }
+    while i < last && classes[i + 1] == CharClass::Whitespace {
+        i += 1;
+    }
+    min(i + 1, last)
+}
+
+fn prev_word_boundary(classes: &[CharClass], from: usize) -> usize {
+    let mut i = from;
+    while i > 0 && classes[i - 1] == CharClass::Whitespace {
+        i -= 1;
+    }
+    if i == 0 {
+        return 0;
+    }
+    i -= 1;
+    let class = classes[i];
+    while i > 0 && classes[i - 1] == class {
+        i -= 1;
+    }
+    i
+}
+
+fn word_end_boundary(classes: &[CharClass], from: usize) -> usize {
+    let last = classes.len() - 1;
+    let mut i = from;
+    while i < last && classes[i + 1] == CharClass::Whitespace {
+        i += 1;
+    }
+    if i == last {
+        return last;
+    }
+    i += 1;
+    let class = classes[i];
+    while i < last && classes[i + 1] == class {
+        i += 1;
+    }
+    i
+}
+
+fn next_paragraph(classes: &[CharClass], from: usize) -> usize {
+    let last = classes.len() - 1;
+    let mut i = from;
+    while i < last && classes[i + 1] != CharClass::Whitespace {
+        i += 1;
+    }
+    min(i + 1, last)
+}
+
+fn prev_paragraph(classes: &[CharClass], from: usize) -> usize {
+    let mut i = from;
+    while i > 0 && classes[i - 1] != CharClass::Whitespace {
+        i -= 1;
+    }
+    i.saturating_sub(1)
+}
+
+enum ViMotion {
+    FileStart,
+    FileEnd,
+    LineStart,
+    LineEnd,
+    ParagraphBackward,
+    ParagraphForward,
+    WordForward(bool),
+    WordBackward(bool),
+    WordEnd(bool),
+}
+
+/// A vi-style "normal mode" layered in front of the view's own key handling: digits accumulate a
+/// count prefix, `g`+`g` and the other motions below move the view by `count` (repeated/positioned
+/// movements dispatched back to the view via `show_data`). Returns `None` when `key` isn't a vi
+/// motion, so the caller falls through to the view's regular handling.
+fn handle_vi_key_event<V: View>(
+    key: &KeyEvent,
+    command: &mut CommandBuf,
+    view: Option<&mut V>,
+) -> Option<Option<Transition>> {
+    if let KeyCode::Char(c @ '1'..='9') = key.code {
+        command.vi_count.push(c);
+        return Some(None);
+    }
+    if key.code == KeyCode::Char('0') && !command.vi_count.is_empty() {
+        command.vi_count.push('0');
+        return Some(None);
+    }
+
+    let motion = match key.code {
+        KeyCode::Char('g') => {
+            if command.vi_pending_g {
+                command.vi_pending_g = false;
+                ViMotion::FileStart
+            } else {
+                command.vi_pending_g = true;
+                return Some(None);
+            }
+        }
+        KeyCode::Char('G') => ViMotion::FileEnd,
+        KeyCode::Char('0') => ViMotion::LineStart,
+        KeyCode::Char('$') => ViMotion::LineEnd,
+        KeyCode::Char('{') => ViMotion::ParagraphBackward,
+        KeyCode::Char('}') => ViMotion::ParagraphForward,
+        KeyCode::Char('w') => ViMotion::WordForward(false),
+        KeyCode::Char('W') => ViMotion::WordForward(true),
+        KeyCode::Char('b') => ViMotion::WordBackward(false),
+        KeyCode::Char('B') => ViMotion::WordBackward(true),
+        KeyCode::Char('e') => ViMotion::WordEnd(false),
+        KeyCode::Char('E') => ViMotion::WordEnd(true),
+        _ => {
+            command.vi_pending_g = false;
+            command.vi_count.clear();
+            return None;
+        }
+    };
+
+    command.vi_pending_g = false;
+    let count = command.vi_count.parse::<usize>().unwrap_or(1).max(1);
+    command.vi_count.clear();
+
+    let view = view?;
+
+    let long_word = matches!(
+        motion,
+        ViMotion::WordForward(true) | ViMotion::WordBackward(true) | ViMotion::WordEnd(true)
+    );
+    let classes = row_classes(view, long_word);
+    if classes.is_empty() {
+        return Some(None);
+    }
+
+    let last = classes.len() - 1;
+    command.vi_row = command.vi_row.min(last);
+
+    let target = match motion {
+        ViMotion::FileStart | ViMotion::LineStart => 0,
+        ViMotion::FileEnd | ViMotion::LineEnd => last,
+        ViMotion::ParagraphBackward => (0..count).fold(command.vi_row, |row, _| {
+            prev_paragraph(&classes, row)
+        }),
+        ViMotion::ParagraphForward => (0..count).fold(command.vi_row, |row, _| {
+            next_paragraph(&classes, row)
+        }),
+        ViMotion::WordForward(_) => (0..count).fold(command.vi_row, |row, _| {
+            next_word_boundary(&classes, row)
+        }),
+        ViMotion::WordBackward(_) => (0..count).fold(command.vi_row, |row, _| {
+            prev_word_boundary(&classes, row)
+        }),
+        ViMotion::WordEnd(_) => (0..count).fold(command.vi_row, |row, _| {
+            word_end_boundary(&classes, row)
+        }),
+    };
+
+    command.vi_row = target;
+    view.show_data(target);
+
+    Some(None)
+}
+
+/// Consult user-defined `keybindings` before any hardcoded handling, so they can rebind exit
+/// chords, search, `:` and the rest. Accumulates chords in `command.key_seq` to support
+/// multi-chord sequences like `"g g"`; returns `None` (falls through to built-in handling)
+/// whenever the sequence-so-far doesn't match or extend any configured binding.
+fn handle_keybinding_event(
+    key: &KeyEvent,
+    keybindings: &KeyBindings,
+    command: &mut CommandBuf,
+    search: &mut SearchBuf,
+    info: &mut ViewInfo,
+) -> Option<Option<Transition>> {
+    if keybindings.bindings.is_empty() {
+        return None;
+    }
+
+    command.key_seq.push(KeyChord {
+        modifiers: key.modifiers,
+        code: key.code,
+    });
+
+    if let Some((_, action)) = keybindings
+        .bindings
+        .iter()
+        .find(|(seq, _)| *seq == command.key_seq)
+    {
+        let action = action.clone();
+        command.key_seq.clear();
+        return Some(run_key_action(action, command, search, info));
+    }
+
+    let is_prefix = keybindings
+        .bindings
+        .iter()
+        .any(|(seq, _)| seq.len() > command.key_seq.len() && seq.starts_with(&command.key_seq));
+
+    if is_prefix {
+        return Some(None);
+    }
+
+    command.key_seq.clear();
+    None
+}
+
+fn run_key_action(
+    action: KeyAction,
+    command: &mut CommandBuf,
+    search: &mut SearchBuf,
+    info: &mut ViewInfo,
+) -> Option<Transition> {
+    match action {
+        KeyAction::Exit => return Some(Transition::Exit),
+        KeyAction::SearchForward => {
+            search.buf_cmd_input = String::new();
+            search.is_search_input = true;
+            search.is_reversed = false;
+            info.report = None;
+        }
+        KeyAction::SearchReverse => {
+            search.buf_cmd_input = String::new();
+            search.is_search_input = true;
+            search.is_reversed = true;
+            info.report = None;
+        }
+        KeyAction::EnterCommand => {
+            command.buf_cmd2 = String::new();
+            command.is_cmd_input = true;
+            command.cmd_exec_info = None;
+            info.report = None;
+        }
+        KeyAction::Command(cmd) => return Some(Transition::Cmd(cmd)),
+        KeyAction::Unknown(name) => {
+            info.report = Some(Report::error(format!("unknown keybinding action: {name}")));
+        }
+    }
+
+    None
 }
 
 fn search_input_key_event(
@@ -875,7 +1844,8 @@ fn search_input_key_event(
             if let Some(view) = view {
                 if !buf.buf_cmd.is_empty() {
                     let data = view.collect_data().into_iter().map(|(text, _)| text);
-                    buf.search_results = search_pattern(data, &buf.buf_cmd, buf.is_reversed);
+                    buf.search_results =
+                        search_pattern(data, &buf.buf_cmd, buf.is_reversed, buf.mode);
                     buf.search_index = 0;
                 }
             }
@@ -890,6 +1860,25 @@ fn search_input_key_event(
 
             true
         }
+        KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+            buf.mode = buf.mode.next();
+
+            if let Some(view) = view {
+                if !buf.buf_cmd_input.is_empty() {
+                    let data = view.collect_data().into_iter().map(|(text, _)| text);
+                    buf.search_results =
+                        search_pattern(data, &buf.buf_cmd_input, buf.is_reversed, buf.mode);
+                    buf.search_index = 0;
+
+                    if !buf.search_results.is_empty() {
+                        let pos = buf.search_results[buf.search_index].row;
+                        view.show_data(pos);
+                    }
+                }
+            }
+
+            true
+        }
         KeyCode::Backspace => {
             if buf.buf_cmd_input.is_empty() {
                 buf.is_search_input = false;
@@ -900,12 +1889,16 @@ fn search_input_key_event(
                 if let Some(view) = view {
                     if !buf.buf_cmd_input.is_empty() {
                         let data = view.collect_data().into_iter().map(|(text, _)| text);
-                        buf.search_results =
-                            search_pattern(data, &buf.buf_cmd_input, buf.is_reversed);
+                        buf.search_results = search_pattern(
+                            data,
+                            &buf.buf_cmd_input,
+                            buf.is_reversed,
+                            buf.mode,
+                        );
                         buf.search_index = 0;
 
                         if !buf.search_results.is_empty() {
-                            let pos = buf.search_results[buf.search_index];
+                            let pos = buf.search_results[buf.search_index].row;
                             view.show_data(pos);
                         }
                     }
@@ -920,11 +1913,12 @@ fn search_input_key_event(
             if let Some(view) = view {
                 if !buf.buf_cmd_input.is_empty() {
                     let data = view.collect_data().into_iter().map(|(text, _)| text);
-                    buf.search_results = search_pattern(data, &buf.buf_cmd_input, buf.is_reversed);
+                    buf.search_results =
+                        search_pattern(data, &buf.buf_cmd_input, buf.is_reversed, buf.mode);
                     buf.search_index = 0;
 
                     if !buf.search_results.is_empty() {
-                        let pos = buf.search_results[buf.search_index];
+                        let pos = buf.search_results[buf.search_index].row;
                         view.show_data(pos);
                     }
                 }
@@ -936,25 +1930,148 @@ fn search_input_key_event(
     }
 }
 
-fn search_pattern(data: impl Iterator<Item = String>, pat: &str, rev: bool) -> Vec<usize> {
+/// Compile `pat` into a [`Regex`], applying a smart-case rule (case-insensitive unless `pat`
+/// contains an uppercase character) and falling back to an escaped literal match if `is_regex`
+/// is set but `pat` doesn't compile as a regex.
+fn build_search_regex(pat: &str, is_regex: bool) -> Regex {
+    let insensitive = !pat.chars().any(|c| c.is_uppercase());
+    let prefix = if insensitive { "(?i)" } else { "" };
+
+    if is_regex {
+        if let Ok(re) = Regex::new(&format!("{prefix}{pat}")) {
+            return re;
+        }
+    }
+
+    Regex::new(&format!("{prefix}{}", regex::escape(pat)))
+        .expect("an escaped literal is always a valid regex")
+}
+
+const FUZZY_SCORE_MATCH: i64 = 16;
+const FUZZY_BONUS_CONSECUTIVE: i64 = 16;
+const FUZZY_BONUS_WORD_BOUNDARY: i64 = 8;
+const FUZZY_GAP_PENALTY: i64 = 1;
+
+/// Whether the character at `cur` starts a new "word" in `text`, for the fuzzy word-boundary
+/// bonus: the very start of the string, right after a separator, or a lower-to-upper transition.
+fn is_fuzzy_word_boundary(prev: Option<char>, cur: char) -> bool {
+    match prev {
+        None => true,
+        Some(p) => matches!(p, '_' | '-' | ' ' | '/' | '.') || (p.is_lowercase() && cur.is_uppercase()),
+    }
+}
+
+/// fzf-style subsequence match: every char of `pat` must appear in order within `text`. Returns
+/// the match score (higher is better) and the char positions in `text` that were matched, or
+/// `None` if `pat` isn't a subsequence of `text`. Case-insensitive unless `pat` contains an
+/// uppercase character (the same smartcase rule as [`build_search_regex`]).
+fn fuzzy_match(pat: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    if pat.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let insensitive = !pat.chars().any(|c| c.is_uppercase());
+    let normalize = |c: char| if insensitive { c.to_ascii_lowercase() } else { c };
+
+    let text_chars: Vec<char> = text.chars().collect();
+    let mut positions = Vec::with_capacity(pat.chars().count());
+    let mut score = 0i64;
+    let mut last_match: Option<usize> = None;
+    let mut search_from = 0usize;
+
+    for pc in pat.chars() {
+        let pc = normalize(pc);
+        let idx = (search_from..text_chars.len()).find(|&i| normalize(text_chars[i]) == pc)?;
+
+        score += FUZZY_SCORE_MATCH;
+        match last_match {
+            Some(prev) if idx == prev + 1 => score += FUZZY_BONUS_CONSECUTIVE,
+            Some(prev) => score -= FUZZY_GAP_PENALTY * (idx - prev - 1) as i64,
+            None => {}
+        }
+
+        let prev_char = idx.checked_sub(1).map(|i| text_chars[i]);
+        if is_fuzzy_word_boundary(prev_char, text_chars[idx]) {
+            score += FUZZY_BONUS_WORD_BOUNDARY;
+        }
+
+        positions.push(idx);
+        last_match = Some(idx);
+        search_from = idx + 1;
+    }
+
+    Some((score, positions))
+}
+
+fn search_pattern(
+    data: impl Iterator<Item = String>,
+    pat: &str,
+    rev: bool,
+    mode: SearchMode,
+) -> Vec<SearchMatch> {
+    if pat.is_empty() {
+        return Vec::new();
+    }
+
+    if mode == SearchMode::Fuzzy {
+        let mut matches: Vec<SearchMatch> = data
+            .enumerate()
+            .filter_map(|(row, text)| {
+                let text = ansi_str::AnsiStr::ansi_strip(&text);
+                let (score, positions) = fuzzy_match(pat, &text)?;
+                let ranges = positions.into_iter().map(|p| (p, p + 1)).collect();
+                Some(SearchMatch { row, ranges, score })
+            })
+            .collect();
+
+        // Descending score, stable on ties by row index.
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.row.cmp(&b.row)));
+        return matches;
+    }
+
+    let regex = build_search_regex(pat, mode == SearchMode::Regex);
+
     let mut matches = Vec::new();
     for (row, text) in data.enumerate() {
-        if text.contains(pat) {
-            matches.push(row);
+        let text = ansi_str::AnsiStr::ansi_strip(&text);
+        let ranges: Vec<(usize, usize)> = regex
+            .find_iter(&text)
+            .map(|m| {
+                (
+                    covert_bytes_to_chars(&text, m.start()),
+                    covert_bytes_to_chars(&text, m.end()),
+                )
+            })
+            .collect();
+
+        if !ranges.is_empty() {
+            matches.push(SearchMatch { row, ranges, score: 0 });
         }
     }
 
     if !rev {
-        matches.sort();
+        matches.sort_by_key(|m| m.row);
     } else {
-        matches.sort_by(|a, b| b.cmp(a));
+        matches.sort_by_key(|m| std::cmp::Reverse(m.row));
     }
 
     matches
 }
 
-fn cmd_input_key_event(buf: &mut CommandBuf, key: &KeyEvent) -> bool {
+fn cmd_input_key_event(
+    buf: &mut CommandBuf,
+    history_path: Option<&std::path::Path>,
+    key: &KeyEvent,
+) -> bool {
+    if buf.is_history_search {
+        return history_search_key_event(buf, history_path, key);
+    }
+
     match &key.code {
+        KeyCode::Esc if !buf.completions.is_empty() => {
+            buf.completions.clear();
+            true
+        }
         KeyCode::Esc => {
             buf.is_cmd_input = false;
             buf.buf_cmd2 = String::new();
@@ -963,11 +2080,41 @@ fn cmd_input_key_event(buf: &mut CommandBuf, key: &KeyEvent) -> bool {
         KeyCode::Enter => {
             buf.is_cmd_input = false;
             buf.run_cmd = true;
-            buf.cmd_history.push(buf.buf_cmd2.clone());
+            buf.completions.clear();
+            push_cmd_history(&mut buf.cmd_history, buf.buf_cmd2.clone());
             buf.cmd_history_pos = buf.cmd_history.len();
+            if let Some(path) = history_path {
+                append_cmd_history(path, &buf.buf_cmd2);
+            }
+            true
+        }
+        KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+            buf.is_history_search = true;
+            buf.pre_search_buf = buf.buf_cmd2.clone();
+            buf.history_search_query = String::new();
+            buf.history_search_matches = history_search_matches(&buf.cmd_history, "");
+            buf.history_search_pos = 0;
+            if let Some(&i) = buf.history_search_matches.first() {
+                buf.buf_cmd2 = buf.cmd_history[i].clone();
+            }
+            true
+        }
+        KeyCode::Tab => {
+            if buf.completions.is_empty() {
+                buf.completions = complete_command_name(&buf.buf_cmd2);
+                buf.completion_index = 0;
+            } else {
+                buf.completion_index = (buf.completion_index + 1) % buf.completions.len();
+            }
+
+            if let Some(candidate) = buf.completions.get(buf.completion_index) {
+                buf.buf_cmd2 = candidate.label.clone();
+            }
+
             true
         }
         KeyCode::Backspace => {
+            buf.completions.clear();
             if buf.buf_cmd2.is_empty() {
                 buf.is_cmd_input = false;
             } else {
@@ -978,6 +2125,7 @@ fn cmd_input_key_event(buf: &mut CommandBuf, key: &KeyEvent) -> bool {
             true
         }
         KeyCode::Char(c) => {
+            buf.completions.clear();
             buf.buf_cmd2.push(*c);
             buf.cmd_history_allow = false;
             true
@@ -1007,6 +2155,53 @@ fn cmd_input_key_event(buf: &mut CommandBuf, key: &KeyEvent) -> bool {
     }
 }
 
+/// Handles keys while a `Ctrl-r` reverse-incremental history search is in progress. Typing
+/// narrows `history_search_matches`; `Ctrl-r`/`Ctrl-s` step toward older/newer matches; `Enter`
+/// accepts the previewed command; `Esc` restores `pre_search_buf` and leaves search mode.
+fn history_search_key_event(
+    buf: &mut CommandBuf,
+    history_path: Option<&std::path::Path>,
+    key: &KeyEvent,
+) -> bool {
+    match &key.code {
+        KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+            if buf.history_search_pos + 1 < buf.history_search_matches.len() {
+                buf.history_search_pos += 1;
+            }
+        }
+        KeyCode::Char('s') if key.modifiers == KeyModifiers::CONTROL => {
+            buf.history_search_pos = buf.history_search_pos.saturating_sub(1);
+        }
+        KeyCode::Esc => {
+            buf.buf_cmd2 = buf.pre_search_buf.clone();
+            buf.is_history_search = false;
+            return true;
+        }
+        KeyCode::Enter => {
+            buf.is_history_search = false;
+            // Fall through to the normal Enter handling with the previewed command in place.
+            return cmd_input_key_event(buf, history_path, key);
+        }
+        KeyCode::Backspace => {
+            buf.history_search_query.pop();
+            buf.history_search_matches = history_search_matches(&buf.cmd_history, &buf.history_search_query);
+            buf.history_search_pos = 0;
+        }
+        KeyCode::Char(c) => {
+            buf.history_search_query.push(*c);
+            buf.history_search_matches = history_search_matches(&buf.cmd_history, &buf.history_search_query);
+            buf.history_search_pos = 0;
+        }
+        _ => return true,
+    }
+
+    if let Some(&i) = buf.history_search_matches.get(buf.history_search_pos) {
+        buf.buf_cmd2 = buf.cmd_history[i].clone();
+    }
+
+    true
+}
+
 fn value_as_style(style: &mut nu_ansi_term::Style, value: &Value) -> bool {
     match value.coerce_str() {
         Ok(s) => {
@@ -1017,53 +2212,112 @@ fn value_as_style(style: &mut nu_ansi_term::Style, value: &Value) -> bool {
     }
 }
 
+/// Recursively writes `value` at `path` within `hm`, creating intermediate records on demand.
+///
+/// If an intermediate segment of `path` already exists but isn't a record, it's overwritten
+/// with a fresh one so the write can proceed — the path the caller asked for always wins over
+/// whatever was there before. Returns `true` only when the full path was applied (i.e. `path`
+/// was non-empty); the updated subtree is always written back into `hm` regardless.
 fn set_config(hm: &mut HashMap<String, Value>, path: &[&str], value: Value) -> bool {
-    if path.is_empty() {
+    let Some((key, rest)) = path.split_first() else {
         return false;
+    };
+
+    if rest.is_empty() {
+        hm.insert((*key).to_string(), value);
+        return true;
     }
 
-    let key = path[0];
+    let mut child: HashMap<String, Value> = match hm.get(*key) {
+        Some(Value::Record { val: record, .. }) => {
+            record.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+        }
+        _ => HashMap::new(),
+    };
 
-    if !hm.contains_key(key) {
-        hm.insert(
-            key.to_string(),
-            Value::record(Record::new(), NuSpan::unknown()),
-        );
+    let applied = set_config(&mut child, rest, value);
+    hm.insert((*key).to_string(), map_into_value(child));
+
+    applied
+}
+
+#[cfg(test)]
+mod set_config_tests {
+    use super::*;
+
+    #[test]
+    fn writes_a_single_segment_path() {
+        let mut hm = HashMap::new();
+        assert!(set_config(&mut hm, &["a"], Value::test_int(1)));
+        assert_eq!(hm.get("a"), Some(&Value::test_int(1)));
     }
 
-    let val = hm.get_mut(key).expect("...");
+    #[test]
+    fn writes_a_two_segment_path() {
+        let mut hm = HashMap::new();
+        assert!(set_config(&mut hm, &["a", "b"], Value::test_int(2)));
 
-    if path.len() == 1 {
-        *val = value;
-        return true;
+        let Some(Value::Record { val: a, .. }) = hm.get("a") else {
+            panic!("expected `a` to be a record");
+        };
+        assert_eq!(a.get("b"), Some(&Value::test_int(2)));
     }
 
-    match val {
-        Value::Record { val: record, .. } => {
-            if path.len() == 2 {
-                let key = path[1];
+    #[test]
+    fn writes_a_three_segment_path() {
+        let mut hm = HashMap::new();
+        assert!(set_config(&mut hm, &["a", "b", "c"], Value::test_int(3)));
 
-                record.insert(key, value);
-            } else {
-                let mut hm2: HashMap<String, Value> = HashMap::new();
-                for (k, v) in record.iter() {
-                    hm2.insert(k.to_string(), v.clone());
-                }
+        let Some(Value::Record { val: a, .. }) = hm.get("a") else {
+            panic!("expected `a` to be a record");
+        };
+        let Some(Value::Record { val: b, .. }) = a.get("b") else {
+            panic!("expected `a.b` to be a record");
+        };
+        assert_eq!(b.get("c"), Some(&Value::test_int(3)));
+    }
 
-                let result = set_config(&mut hm2, &path[1..], value);
-                if !result {
-                    *val = map_into_value(hm2);
-                }
+    #[test]
+    fn writes_a_four_segment_path() {
+        let mut hm = HashMap::new();
+        assert!(set_config(&mut hm, &["a", "b", "c", "d"], Value::test_int(4)));
 
-                if path.len() == 2 {
-                } else {
-                    return false;
-                }
-            }
+        let Some(Value::Record { val: a, .. }) = hm.get("a") else {
+            panic!("expected `a` to be a record");
+        };
+        let Some(Value::Record { val: b, .. }) = a.get("b") else {
+            panic!("expected `a.b` to be a record");
+        };
+        let Some(Value::Record { val: c, .. }) = b.get("c") else {
+            panic!("expected `a.b.c` to be a record");
+        };
+        assert_eq!(c.get("d"), Some(&Value::test_int(4)));
+    }
 
-            true
-        }
-        _ => false,
+    #[test]
+    fn a_deep_write_does_not_clobber_sibling_keys() {
+        let mut hm = HashMap::new();
+        set_config(&mut hm, &["a", "b"], Value::test_int(1));
+        set_config(&mut hm, &["a", "c"], Value::test_int(2));
+
+        let Some(Value::Record { val: a, .. }) = hm.get("a") else {
+            panic!("expected `a` to be a record");
+        };
+        assert_eq!(a.get("b"), Some(&Value::test_int(1)));
+        assert_eq!(a.get("c"), Some(&Value::test_int(2)));
+    }
+
+    #[test]
+    fn overwrites_a_non_record_intermediate_segment() {
+        let mut hm = HashMap::new();
+        hm.insert("a".to_string(), Value::test_int(0));
+
+        assert!(set_config(&mut hm, &["a", "b"], Value::test_int(5)));
+
+        let Some(Value::Record { val: a, .. }) = hm.get("a") else {
+            panic!("expected the non-record `a` to have been replaced with a record");
+        };
+        assert_eq!(a.get("b"), Some(&Value::test_int(5)));
     }
 }
 
@@ -1124,6 +2378,82 @@ impl ViewStack {
     }
 }
 
+/// Whether a [`Layer`] consumes all input (blocking lower layers even when it doesn't handle a
+/// key itself) or is a transient popup that lets unhandled events fall through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayerKind {
+    /// Opaque and modal, e.g. the command bar: draws over the full area and owns all input.
+    Modal,
+    /// Renders into a `Rect` centered in the available area, sized as a percentage of it, and
+    /// defers unhandled input to the layers below.
+    Popup {
+        width_percent: u16,
+        height_percent: u16,
+    },
+}
+
+struct Layer {
+    view: Box<dyn View>,
+    kind: LayerKind,
+}
+
+/// An ordered stack of layers drawn bottom-to-top above the base view, without disturbing
+/// `ViewStack`. Lets transient UI (help screens, a command palette, popups) render over the live
+/// view instead of replacing it. See [`Pager::push_layer`]/[`Pager::pop_layer`].
+#[derive(Default)]
+struct Compositor {
+    layers: Vec<Layer>,
+}
+
+#[allow(dead_code)]
+impl Compositor {
+    fn push(&mut self, view: Box<dyn View>, kind: LayerKind) {
+        self.layers.push(Layer { view, kind });
+    }
+
+    fn pop(&mut self) -> Option<Layer> {
+        self.layers.pop()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Dispatch to the topmost layer first, falling through to the next one down only when it
+    /// returns unhandled (`None`) and isn't [`LayerKind::Modal`]. The outer `Option` reports
+    /// whether any layer consumed the event at all.
+    fn handle_event(
+        &mut self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        layout: &Layout,
+        info: &mut ViewInfo,
+        key: KeyEvent,
+    ) -> Option<Option<Transition>> {
+        for layer in self.layers.iter_mut().rev() {
+            let result = layer.view.handle_input(engine_state, stack, layout, info, key);
+            if result.is_some() {
+                return Some(result);
+            }
+
+            if layer.kind == LayerKind::Modal {
+                return Some(None);
+            }
+        }
+
+        None
+    }
+}
+
+fn centered_rect(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
+    let width = area.width * width_percent.min(100) / 100;
+    let height = area.height * height_percent.min(100) / 100;
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+
+    Rect::new(x, y, width, height)
+}
+
 struct CmdResult {
     exit: bool,
     view_change: bool,
@@ -1,13 +1,52 @@
-use super::{create_command, make_plugin_interface, PluginExecutionCommandContext};
+use super::{
+    create_command, make_plugin_interface, PluginExecutionCommandContext, PluginInterface,
+};
 use crate::protocol::{CallInfo, EvaluatedCall};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use nu_engine::eval_block;
 use nu_protocol::engine::{Command, EngineState, Stack};
 use nu_protocol::{ast::Call, PluginSignature, Signature};
 use nu_protocol::{Example, PipelineData, ShellError, Value};
 
+/// How long a spawned plugin process is kept around after its last use before the idle reaper
+/// shuts it down. Keeping this short means a plugin that's used in a burst (e.g. in a loop)
+/// doesn't pay the spawn/handshake cost on every call, without processes lingering forever.
+const PLUGIN_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct RegisteredPlugin {
+    interface: PluginInterface,
+    last_used: Instant,
+}
+
+/// Plugin processes that are still running, keyed by the plugin's binary path, so that repeated
+/// calls to the same plugin within [`PLUGIN_IDLE_TIMEOUT`] can reuse the existing process and
+/// its already-completed handshake instead of spawning and re-negotiating one every time.
+static PLUGIN_REGISTRY: OnceLock<Mutex<HashMap<PathBuf, RegisteredPlugin>>> = OnceLock::new();
+
+fn plugin_registry() -> &'static Mutex<HashMap<PathBuf, RegisteredPlugin>> {
+    PLUGIN_REGISTRY.get_or_init(|| {
+        // Start the idle reaper the first time the registry is touched; there's only ever one.
+        std::thread::Builder::new()
+            .name("plugin idle reaper".into())
+            .spawn(reap_idle_plugins)
+            .expect("failed to spawn plugin idle reaper thread");
+        Mutex::new(HashMap::new())
+    })
+}
+
+fn reap_idle_plugins() {
+    loop {
+        std::thread::sleep(PLUGIN_IDLE_TIMEOUT / 2);
+        if let Ok(mut registry) = PLUGIN_REGISTRY.get().expect("registry initialized").lock() {
+            registry.retain(|_, plugin| plugin.last_used.elapsed() < PLUGIN_IDLE_TIMEOUT);
+        }
+    }
+}
+
 #[doc(hidden)] // Note: not for plugin authors / only used in nu-parser
 #[derive(Clone)]
 pub struct PluginDeclaration {
@@ -26,6 +65,45 @@ impl PluginDeclaration {
             shell,
         }
     }
+
+    /// Spawn a fresh plugin process, complete its handshake, and register it so future calls to
+    /// this same plugin can reuse it instead of spawning again.
+    fn spawn_and_register(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+    ) -> Result<PluginInterface, ShellError> {
+        let source_file = Path::new(&self.filename);
+        let mut plugin_cmd = create_command(source_file, self.shell.as_deref());
+        // We need the current environment variables for `python` based plugins
+        // Or we'll likely have a problem when a plugin is implemented in a virtual Python environment.
+        let current_envs = nu_engine::env::env_to_strings(engine_state, stack).unwrap_or_default();
+        plugin_cmd.envs(current_envs);
+
+        let child = plugin_cmd.spawn().map_err(|err| {
+            let decl = engine_state.get_decl(call.decl_id);
+            ShellError::GenericError {
+                error: format!("Unable to spawn plugin for {}", decl.name()),
+                msg: format!("{err}"),
+                span: Some(call.head),
+                help: None,
+                inner: vec![],
+            }
+        })?;
+
+        let plugin = make_plugin_interface(child)?;
+
+        plugin_registry().lock().expect("plugin registry poisoned").insert(
+            self.filename.clone(),
+            RegisteredPlugin {
+                interface: plugin.clone(),
+                last_used: Instant::now(),
+            },
+        );
+
+        Ok(plugin)
+    }
 }
 
 impl Command for PluginDeclaration {
@@ -114,26 +192,6 @@ impl Command for PluginDeclaration {
                 }
             });
 
-        // Set up the plugin command to execute
-        let source_file = Path::new(&self.filename);
-        let mut plugin_cmd = create_command(source_file, self.shell.as_deref());
-        // We need the current environment variables for `python` based plugins
-        // Or we'll likely have a problem when a plugin is implemented in a virtual Python environment.
-        let current_envs = nu_engine::env::env_to_strings(engine_state, stack).unwrap_or_default();
-        plugin_cmd.envs(current_envs);
-
-        // Run the plugin command
-        let child = plugin_cmd.spawn().map_err(|err| {
-            let decl = engine_state.get_decl(call.decl_id);
-            ShellError::GenericError {
-                error: format!("Unable to spawn plugin for {}", decl.name()),
-                msg: format!("{err}"),
-                span: Some(call.head),
-                help: None,
-                inner: vec![],
-            }
-        })?;
-
         // Create the context to execute in - this supports engine calls and custom values
         let context = Arc::new(PluginExecutionCommandContext::new(
             self.filename.clone(),
@@ -143,17 +201,78 @@ impl Command for PluginDeclaration {
             call,
         ));
 
-        let plugin = make_plugin_interface(child)?;
+        // Reuse an already-running plugin process if we have one cached, rather than paying the
+        // spawn + handshake cost again for every call to the same plugin.
+        let reused = plugin_registry()
+            .lock()
+            .expect("plugin registry poisoned")
+            .get_mut(&self.filename)
+            .map(|plugin| {
+                plugin.last_used = Instant::now();
+                plugin.interface.clone()
+            });
 
-        plugin.run(
+        let (plugin, was_reused) = match reused {
+            Some(plugin) => (plugin, true),
+            None => (self.spawn_and_register(engine_state, stack, call)?, false),
+        };
+
+        // A reused process may have died or closed its pipe since we last used it (e.g. the
+        // plugin crashed, or exited due to its own idle timeout). If so, and `input` is cheap to
+        // clone (it isn't a stream that's unsafe to replay), retry exactly once against a freshly
+        // spawned process rather than surfacing a confusing "broken pipe" error for something the
+        // user didn't do wrong. A stream input can't be resent after a failed attempt may have
+        // already consumed part of it, so those just surface the original error.
+        let retry_input = match &input {
+            PipelineData::Value(value, metadata) => {
+                Some(PipelineData::Value(value.clone(), metadata.clone()))
+            }
+            PipelineData::Empty => Some(PipelineData::Empty),
+            PipelineData::ListStream(..) | PipelineData::ExternalStream { .. } => None,
+        };
+
+        let result = plugin.run(
             CallInfo {
                 name: self.name.clone(),
                 call: evaluated_call,
                 input,
-                config,
+                config: config.clone(),
             },
-            context
-        )
+            context.clone(),
+        );
+
+        // `run` isn't idempotent the way `get_signature`/`custom_value_to_base_value` are - the
+        // plugin may have already had side effects before reporting an error - so this can only
+        // retry when the error means the old connection itself is dead, never for an error the
+        // plugin deliberately reported over a connection that's still fine. `ShellError::IOError`
+        // is what a transport-level failure (a write/flush against a closed pipe, or garbage on
+        // the wire where a response was expected) surfaces as; a plugin-reported command failure
+        // comes back as some other `ShellError` variant instead, so matching on this specifically
+        // avoids re-running a command whose first attempt already ran to completion and failed on
+        // its own merits.
+        let was_dead_connection = matches!(&result, Err(ShellError::IOError { .. }));
+
+        if was_reused && was_dead_connection {
+            if let Some(retry_input) = retry_input {
+                plugin_registry()
+                    .lock()
+                    .expect("plugin registry poisoned")
+                    .remove(&self.filename);
+                let evaluated_call = EvaluatedCall::try_from_call(call, engine_state, stack)?;
+                let plugin = self.spawn_and_register(engine_state, stack, call)?;
+                return plugin.run(
+                    CallInfo {
+                        name: self.name.clone(),
+                        call: evaluated_call,
+                        input: retry_input,
+                        config,
+                    },
+                    context,
+                );
+            }
+        }
+
+        result
     }
 
     fn is_plugin(&self) -> Option<(&Path, Option<&Path>)> {
@@ -1,22 +1,28 @@
 //! Interface used by the plugin to communicate with the engine.
 
-use std::sync::{mpsc, Arc};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use nu_protocol::{
-    engine::Closure, Config, IntoInterruptiblePipelineData, ListStream, PipelineData,
-    PluginSignature, ShellError, Spanned, Value,
+    engine::{Closure, JsonDiagnosticLevel}, Config, DataSource, DeclId,
+    IntoInterruptiblePipelineData, ListStream, PipelineData, PipelineMetadata, PluginSignature,
+    ShellError, Span, Spanned, Value,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     protocol::{
         CallInfo, CustomValueOp, EngineCall, EngineCallId, EngineCallResponse, PluginCall,
         PluginCallId, PluginCallResponse, PluginCustomValue, PluginInput, ProtocolInfo,
     },
-    LabeledError, PluginOutput,
+    EvaluatedCall, LabeledError, PluginOutput,
 };
 
 use super::{
-    stream::{StreamManager, StreamManagerHandle},
+    plugin::{DiagnosticSummary, EngineCallError},
+    stream::{StreamManager, StreamManagerHandle, StreamWindowConfig},
     Interface, InterfaceManager, PluginRead, PluginWrite,
 };
 use crate::sequence::Sequence;
@@ -56,8 +62,64 @@ struct EngineInterfaceState {
         mpsc::Sender<(EngineCallId, mpsc::Sender<EngineCallResponse<PipelineData>>)>,
     /// The synchronized output writer
     writer: Box<dyn PluginWrite<PluginOutput>>,
+    /// How long [`EngineInterface::engine_call`] waits for a response before giving up, rather
+    /// than blocking forever if the engine never replies (a dropped context, a hung closure, a
+    /// protocol desync). `None` waits indefinitely.
+    call_timeout: Option<Duration>,
+    /// Checked while waiting for an engine call response, so a call that's wedged waiting on the
+    /// engine can still be cancelled by the same Ctrl-C that would otherwise only interrupt the
+    /// plugin's own closure evaluation.
+    interrupt: Option<Arc<AtomicBool>>,
+    /// Optional features supported by both us and the engine, computed as the intersection of
+    /// what each side advertised in `Hello`. Lives in the shared state (rather than on
+    /// [`EngineInterfaceManager`] alongside `negotiated_encoding`/`negotiated_transport`) so that
+    /// [`EngineInterface::supports`] can query it directly. Empty until the handshake completes.
+    negotiated_features: RwLock<Vec<Feature>>,
+    /// Flow control window for a background stream writer (see `StreamWriterSignal` in
+    /// `stream.rs`), bounding how many bytes of an `eval_closure_with_stream`/`write_response`
+    /// output a slow engine lets this plugin buffer in memory before blocking. Configurable via
+    /// [`EngineInterfaceManager::set_stream_window_config`]. Wiring this all the way through to
+    /// `StreamManagerHandle::write_stream`'s `window` parameter happens in
+    /// `Interface::init_write_pipeline_data`'s default implementation, which isn't part of this
+    /// module, so for now this is just where that value would be read from.
+    stream_window_config: StreamWindowConfig,
+    /// The content types this plugin declared (e.g. in its signature) that it knows how to read
+    /// from an incoming `PipelineData::ExternalStream`. `None` means the plugin didn't declare
+    /// anything and accepts any content type, including none. Checked in `prepare_pipeline_data`
+    /// against the stream's `PipelineMetadata::content_type`, if it has one.
+    accepted_input_content_types: Option<Vec<String>>,
+    /// The content type this plugin declared that it produces on the `ExternalStream` output it
+    /// hands back to the engine, if any. Applied by
+    /// [`EngineInterface::tag_output_content_type`].
+    declared_output_content_type: Option<String>,
+    /// Opt-in configuration for farming custom-value (de)serialization of a `ListStream` out
+    /// across a worker pool instead of mapping it lazily one value at a time. `None` (the
+    /// default) keeps the original lazy, single-threaded behavior, which is the right choice for
+    /// small or latency-sensitive streams.
+    parallel_serialize: Option<ParallelSerializeConfig>,
 }
 
+/// Control knobs for the opt-in parallel custom-value (de)serialization path, set via
+/// [`EngineInterfaceManager::set_parallel_serialize`] (e.g. from the plugin's own config).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParallelSerializeConfig {
+    /// How many values to buffer from the stream before farming them out to the worker pool.
+    /// A window of `0` disables parallel serialization even if configured, falling back to the
+    /// lazy path, since there'd be nothing to buffer.
+    pub window: usize,
+    /// How many worker threads to split each window across.
+    pub workers: usize,
+}
+
+/// The default for [`EngineInterfaceState::stream_window_config`] when not otherwise configured:
+/// a modest starting window that can auto-tune up to 64x itself if the engine turns out to be
+/// the bottleneck.
+const DEFAULT_STREAM_WINDOW_CONFIG: StreamWindowConfig = StreamWindowConfig {
+    starting: 1024 * 1024,
+    ceiling: 64 * 1024 * 1024,
+    auto_tune: true,
+};
+
 impl std::fmt::Debug for EngineInterfaceState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("EngineInterfaceState")
@@ -67,6 +129,25 @@ impl std::fmt::Debug for EngineInterfaceState {
                 "engine_call_subscription_sender",
                 &self.engine_call_subscription_sender,
             )
+            .field("call_timeout", &self.call_timeout)
+            .field("stream_window_config", &self.stream_window_config)
+            .field(
+                "accepted_input_content_types",
+                &self.accepted_input_content_types,
+            )
+            .field(
+                "declared_output_content_type",
+                &self.declared_output_content_type,
+            )
+            .field("parallel_serialize", &self.parallel_serialize)
+            .field(
+                "negotiated_features",
+                &self
+                    .negotiated_features
+                    .read()
+                    .map(|features| features.iter().map(|f| f.as_str()).collect::<Vec<_>>())
+                    .unwrap_or_default(),
+            )
             .finish_non_exhaustive()
     }
 }
@@ -89,6 +170,129 @@ pub(crate) struct EngineInterfaceManager {
     stream_manager: StreamManager,
     /// Protocol version info, set after `Hello` received
     protocol_info: Option<ProtocolInfo>,
+    /// The encoding chosen during the `Hello` handshake. See the matching type in `plugin.rs`
+    /// for the engine side of this negotiation.
+    negotiated_encoding: Option<EncodingType>,
+    /// The transport chosen during the `Hello` handshake, picked as the first mutually supported
+    /// entry in `ProtocolInfo::transports`, in our own preference order. See `negotiate_transport`
+    /// in `plugin.rs` for the engine side of this negotiation. `Some("stdio")` when stdio is the
+    /// best transport both sides offer; `None` until `Hello` arrives, or if the engine offered no
+    /// transport we support.
+    negotiated_transport: Option<String>,
+}
+
+/// A wire encoding the plugin protocol can be serialized with. `MsgPack` is preferred for
+/// binary values and large tables since it avoids the base64/escaping overhead `Json` needs for
+/// binary data; `Json` remains available as the compatibility fallback for plugins that only
+/// implement it. `Bincode` is an additional, faster option for plugins written against this
+/// engine specifically, since it isn't a format other implementations are expected to support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EncodingType {
+    Bincode,
+    MsgPack,
+    Json,
+}
+
+impl EncodingType {
+    fn as_str(self) -> &'static str {
+        match self {
+            EncodingType::Bincode => "bincode",
+            EncodingType::MsgPack => "msgpack",
+            EncodingType::Json => "json",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<EncodingType> {
+        match name {
+            "bincode" => Some(EncodingType::Bincode),
+            "msgpack" => Some(EncodingType::MsgPack),
+            "json" => Some(EncodingType::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Our preferred encodings, most preferred first.
+const SUPPORTED_ENCODINGS: &[EncodingType] = &[
+    EncodingType::Bincode,
+    EncodingType::MsgPack,
+    EncodingType::Json,
+];
+
+fn negotiate_encoding(remote_encodings: &[String]) -> Option<EncodingType> {
+    SUPPORTED_ENCODINGS.iter().copied().find(|ours| {
+        remote_encodings
+            .iter()
+            .any(|theirs| EncodingType::from_str(theirs) == Some(*ours))
+    })
+}
+
+/// Our preferred transports, most preferred first. Mirrors `SUPPORTED_TRANSPORTS` in `plugin.rs`;
+/// see there for what each option means.
+const SUPPORTED_TRANSPORTS: &[&str] = &["local_socket", "stdio"];
+
+/// Pick the best mutually supported transport, in our own preference order. Mirrors
+/// `negotiate_transport` in `plugin.rs`.
+///
+/// Actually moving off of stdio onto the negotiated transport - connecting out to the socket
+/// address the engine advertised in its `Hello`, and swapping this manager's reader/writer over
+/// to it - needs to happen wherever the initial reader/writer this manager was constructed with
+/// came from, which is outside this module (it's whatever set up our stdio in the first place).
+/// For now this only records what was negotiated.
+fn negotiate_transport(remote_transports: &[String]) -> Option<&'static str> {
+    SUPPORTED_TRANSPORTS
+        .iter()
+        .copied()
+        .find(|ours| remote_transports.iter().any(|theirs| theirs == ours))
+}
+
+/// An optional behavior that isn't required for the protocol to function, but that either side
+/// may or may not implement yet (a new transport, a new kind of engine call, stream compression).
+/// Gating a behavior behind a `Feature` lets it be introduced without breaking compatibility with
+/// an engine or plugin built before that behavior existed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// The `local_socket` transport from `negotiate_transport`, queryable as a feature too so a
+    /// plugin can check support before ever attempting the handshake field that advertises it.
+    LocalSocket,
+    /// Compression applied to `PluginOutput::Stream`/`PluginInput::Stream` frames.
+    StreamCompression,
+}
+
+impl Feature {
+    fn as_str(self) -> &'static str {
+        match self {
+            Feature::LocalSocket => "local_socket",
+            Feature::StreamCompression => "stream_compression",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<Feature> {
+        match name {
+            "local_socket" => Some(Feature::LocalSocket),
+            "stream_compression" => Some(Feature::StreamCompression),
+            _ => None,
+        }
+    }
+}
+
+/// Optional features we implement. Unlike encodings/transports, there's no preference order here
+/// - every mutually supported feature is retained, not just the best one.
+const SUPPORTED_FEATURES: &[Feature] = &[Feature::LocalSocket, Feature::StreamCompression];
+
+/// Compute the features usable this session: ours, filtered down to the ones the other side also
+/// advertised. A feature string neither side recognizes is just absent from both lists, so it's
+/// silently ignored rather than rejected - that's what keeps this forward compatible.
+fn negotiate_features(remote_features: &[String]) -> Vec<Feature> {
+    SUPPORTED_FEATURES
+        .iter()
+        .copied()
+        .filter(|ours| {
+            remote_features
+                .iter()
+                .any(|theirs| Feature::from_str(theirs) == Some(*ours))
+        })
+        .collect()
 }
 
 impl EngineInterfaceManager {
@@ -102,13 +306,109 @@ impl EngineInterfaceManager {
                 stream_id_sequence: Sequence::default(),
                 engine_call_subscription_sender: subscription_tx,
                 writer: Box::new(writer),
+                call_timeout: None,
+                interrupt: None,
+                negotiated_features: RwLock::new(Vec::new()),
+                stream_window_config: DEFAULT_STREAM_WINDOW_CONFIG,
+                accepted_input_content_types: None,
+                declared_output_content_type: None,
+                parallel_serialize: None,
             }),
             plugin_call_sender: plug_tx,
             plugin_call_receiver: Some(plug_rx),
             engine_call_subscriptions: vec![],
             engine_call_subscription_receiver: subscription_rx,
-            stream_manager: StreamManager::new(),
+            // Keepalive is opt-in; `None` preserves the old wait-forever behavior.
+            stream_manager: StreamManager::new(None),
             protocol_info: None,
+            negotiated_encoding: None,
+            negotiated_transport: None,
+        }
+    }
+
+    /// Set how long [`EngineInterface::engine_call`] will wait for a response before giving up.
+    /// Must be called before any [`EngineInterface`] has been handed out (e.g. right after
+    /// [`new`](Self::new)), since the timeout lives in the state shared with those interfaces.
+    pub(crate) fn set_call_timeout(&mut self, timeout: Option<Duration>) {
+        Arc::get_mut(&mut self.state)
+            .expect("set_call_timeout called after interfaces were already created")
+            .call_timeout = timeout;
+    }
+
+    /// Set the interrupt signal checked while waiting for an engine call response, so a call that
+    /// never hears back can still be cancelled. Must be called before any [`EngineInterface`] has
+    /// been handed out, for the same reason as [`set_call_timeout`](Self::set_call_timeout).
+    pub(crate) fn set_interrupt(&mut self, interrupt: Option<Arc<AtomicBool>>) {
+        Arc::get_mut(&mut self.state)
+            .expect("set_interrupt called after interfaces were already created")
+            .interrupt = interrupt;
+    }
+
+    /// Set the flow control window used for background stream writes. Must be called before any
+    /// [`EngineInterface`] has been handed out, for the same reason as
+    /// [`set_call_timeout`](Self::set_call_timeout).
+    pub(crate) fn set_stream_window_config(&mut self, window: StreamWindowConfig) {
+        Arc::get_mut(&mut self.state)
+            .expect("set_stream_window_config called after interfaces were already created")
+            .stream_window_config = window;
+    }
+
+    /// Declare which `content_type`s this plugin accepts on an incoming
+    /// `PipelineData::ExternalStream`. `None` (the default) accepts anything. Must be called
+    /// before any [`EngineInterface`] has been handed out, for the same reason as
+    /// [`set_call_timeout`](Self::set_call_timeout).
+    pub(crate) fn set_accepted_input_content_types(&mut self, content_types: Option<Vec<String>>) {
+        Arc::get_mut(&mut self.state)
+            .expect("set_accepted_input_content_types called after interfaces were already created")
+            .accepted_input_content_types = content_types;
+    }
+
+    /// Declare the `content_type` this plugin produces on the `ExternalStream` output it returns,
+    /// so [`EngineInterface::tag_output_content_type`] can apply it automatically. Must be called
+    /// before any [`EngineInterface`] has been handed out, for the same reason as
+    /// [`set_call_timeout`](Self::set_call_timeout).
+    pub(crate) fn set_declared_output_content_type(&mut self, content_type: Option<String>) {
+        Arc::get_mut(&mut self.state)
+            .expect("set_declared_output_content_type called after interfaces were already created")
+            .declared_output_content_type = content_type;
+    }
+
+    /// Configure (or disable) parallel custom-value (de)serialization for `ListStream`s, e.g.
+    /// from the plugin's own config. Must be called before any [`EngineInterface`] has been
+    /// handed out, for the same reason as [`set_call_timeout`](Self::set_call_timeout).
+    pub(crate) fn set_parallel_serialize(&mut self, config: Option<ParallelSerializeConfig>) {
+        Arc::get_mut(&mut self.state)
+            .expect("set_parallel_serialize called after interfaces were already created")
+            .parallel_serialize = config;
+    }
+
+    /// Check `metadata`'s `content_type` (if any) against
+    /// [`accepted_input_content_types`](EngineInterfaceState::accepted_input_content_types), if
+    /// the plugin declared one. Passes silently if either side didn't declare anything, since
+    /// there's nothing to disagree about.
+    fn check_accepted_content_type(
+        &self,
+        metadata: Option<&PipelineMetadata>,
+    ) -> Result<(), ShellError> {
+        let Some(accepted) = &self.state.accepted_input_content_types else {
+            return Ok(());
+        };
+        let Some(content_type) = metadata.and_then(|meta| meta.content_type.as_deref()) else {
+            return Ok(());
+        };
+        if accepted.iter().any(|accepted| accepted == content_type) {
+            Ok(())
+        } else {
+            Err(ShellError::GenericError {
+                error: "Unsupported content type".into(),
+                msg: format!(
+                    "this plugin only accepts {accepted:?}, but the input stream is \
+                        `{content_type}`"
+                ),
+                span: None,
+                help: None,
+                inner: vec![],
+            })
         }
     }
 
@@ -209,6 +509,28 @@ impl InterfaceManager for EngineInterfaceManager {
             PluginInput::Hello(info) => {
                 let local_info = ProtocolInfo::default();
                 if local_info.is_compatible_with(&info)? {
+                    self.negotiated_encoding =
+                        Some(negotiate_encoding(&info.encodings).ok_or_else(|| {
+                            ShellError::PluginFailedToLoad {
+                                msg: format!(
+                                    "Engine offered no encoding we support ({:?}); \
+                                    we support {:?}",
+                                    info.encodings,
+                                    SUPPORTED_ENCODINGS
+                                        .iter()
+                                        .map(|e| e.as_str())
+                                        .collect::<Vec<_>>(),
+                                ),
+                            }
+                        })?);
+                    self.negotiated_transport =
+                        negotiate_transport(&info.transports).map(Into::into);
+                    *self
+                        .state
+                        .negotiated_features
+                        .write()
+                        .expect("negotiated_features lock poisoned") =
+                        negotiate_features(&info.features);
                     self.protocol_info = Some(info);
                     Ok(())
                 } else {
@@ -280,6 +602,10 @@ impl InterfaceManager for EngineInterfaceManager {
                 let response = match response {
                     EngineCallResponse::Error(err) => EngineCallResponse::Error(err),
                     EngineCallResponse::Config(config) => EngineCallResponse::Config(config),
+                    EngineCallResponse::DeclId(decl_id) => EngineCallResponse::DeclId(decl_id),
+                    EngineCallResponse::DiagnosticSummary(summary) => {
+                        EngineCallResponse::DiagnosticSummary(summary)
+                    }
                     EngineCallResponse::PipelineData(header) => {
                         // If there's an error with initializing this stream, change it to an engine
                         // call error response, but send it anyway
@@ -305,17 +631,137 @@ impl InterfaceManager for EngineInterfaceManager {
                 let value = PluginCustomValue::deserialize_custom_values_in(value)?;
                 Ok(PipelineData::Value(value, meta))
             }
-            PipelineData::ListStream(ListStream { stream, ctrlc, .. }, meta) => Ok(stream
-                .map(|value| {
-                    let span = value.span();
-                    match PluginCustomValue::deserialize_custom_values_in(value) {
-                        Ok(value) => value,
-                        Err(err) => Value::error(err, span),
-                    }
+            PipelineData::ListStream(ListStream { stream, ctrlc, .. }, meta) => {
+                let parallel = self.state.parallel_serialize;
+                Ok(map_custom_values(stream, parallel, |value| {
+                    PluginCustomValue::deserialize_custom_values_in(value)
                 })
-                .into_pipeline_data_with_metadata(meta, ctrlc)),
-            PipelineData::Empty | PipelineData::ExternalStream { .. } => Ok(data),
+                .into_pipeline_data_with_metadata(meta, ctrlc))
+            }
+            PipelineData::Empty => Ok(PipelineData::Empty),
+            PipelineData::ExternalStream { ref metadata, .. } => {
+                self.check_accepted_content_type(metadata.as_ref())?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+/// Map `f` (a custom-value serialize/deserialize step) over `stream`, converting any error into a
+/// `Value::error` at that value's own span - same as the plain `.map()` this replaces. When
+/// `parallel` is configured with a nonzero window, values are buffered in windows of that size
+/// and farmed out across `parallel.workers` threads, preserving their original order on output;
+/// otherwise each value is mapped lazily, one at a time, as before.
+fn map_custom_values<F>(
+    stream: impl Iterator<Item = Value> + Send + 'static,
+    parallel: Option<ParallelSerializeConfig>,
+    f: F,
+) -> Box<dyn Iterator<Item = Value> + Send>
+where
+    F: Fn(Value) -> Result<Value, ShellError> + Clone + Send + Sync + 'static,
+{
+    match parallel {
+        Some(config) if config.window > 0 && config.workers > 1 => {
+            Box::new(ParallelMappedStream::new(stream, config, f))
+        }
+        _ => Box::new(stream.map(move |value| {
+            let span = value.span();
+            match f(value) {
+                Ok(value) => value,
+                Err(err) => Value::error(err, span),
+            }
+        })),
+    }
+}
+
+/// Iterator adapter backing the parallel branch of [`map_custom_values`]. Pulls a window of
+/// values from `upstream`, splits it into up to `workers` contiguous, roughly-equal slices, and
+/// serializes each slice on its own thread - then replays the results in original order before
+/// pulling the next window. Bounded to one window in flight at a time, so memory use stays
+/// proportional to the window size rather than the whole stream.
+struct ParallelMappedStream<I, F> {
+    upstream: I,
+    window: usize,
+    workers: usize,
+    f: F,
+    buffer: std::vec::IntoIter<Value>,
+}
+
+impl<I, F> ParallelMappedStream<I, F>
+where
+    I: Iterator<Item = Value>,
+    F: Fn(Value) -> Result<Value, ShellError> + Clone + Send + Sync,
+{
+    fn new(upstream: I, config: ParallelSerializeConfig, f: F) -> Self {
+        ParallelMappedStream {
+            upstream,
+            window: config.window,
+            workers: config.workers.max(1),
+            f,
+            buffer: Vec::new().into_iter(),
+        }
+    }
+
+    fn fill_buffer(&mut self) {
+        let chunk: Vec<Value> = (&mut self.upstream).take(self.window).collect();
+        if chunk.is_empty() {
+            self.buffer = Vec::new().into_iter();
+            return;
+        }
+
+        let worker_count = self.workers.min(chunk.len()).max(1);
+        let slice_len = (chunk.len() + worker_count - 1) / worker_count;
+
+        let mut slices = Vec::with_capacity(worker_count);
+        let mut rest = chunk;
+        while !rest.is_empty() {
+            let tail = rest.split_off(slice_len.min(rest.len()));
+            slices.push(rest);
+            rest = tail;
+        }
+
+        let results: Vec<Value> = std::thread::scope(|scope| {
+            let handles: Vec<_> = slices
+                .into_iter()
+                .map(|slice| {
+                    let f = self.f.clone();
+                    scope.spawn(move || {
+                        slice
+                            .into_iter()
+                            .map(|value| {
+                                let span = value.span();
+                                match f(value) {
+                                    Ok(value) => value,
+                                    Err(err) => Value::error(err, span),
+                                }
+                            })
+                            .collect::<Vec<Value>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("parallel serialize worker panicked"))
+                .collect()
+        });
+
+        self.buffer = results.into_iter();
+    }
+}
+
+impl<I, F> Iterator for ParallelMappedStream<I, F>
+where
+    I: Iterator<Item = Value>,
+    F: Fn(Value) -> Result<Value, ShellError> + Clone + Send + Sync,
+{
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        if let Some(value) = self.buffer.next() {
+            return Some(value);
         }
+        self.fill_buffer();
+        self.buffer.next()
     }
 }
 
@@ -355,6 +801,27 @@ impl EngineInterface {
         self.flush()
     }
 
+    /// Check whether an optional feature is supported by both us and the connected engine, as
+    /// negotiated during the `Hello` handshake. Always `false` before the handshake completes.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.state
+            .negotiated_features
+            .read()
+            .map(|features| features.contains(&feature))
+            .unwrap_or(false)
+    }
+
+    /// Apply this plugin's declared output content type (see
+    /// [`EngineInterfaceManager::set_declared_output_content_type`]) to `data`'s metadata, if one
+    /// was declared. A plugin command can call this on the `PipelineData` it's about to return
+    /// instead of tagging the content type by hand on every call.
+    pub fn tag_output_content_type(&self, data: PipelineData) -> PipelineData {
+        match &self.state.declared_output_content_type {
+            Some(content_type) => tag_content_type(data, content_type.clone()),
+            None => data,
+        }
+    }
+
     fn context(&self) -> Result<PluginCallId, ShellError> {
         self.context.ok_or_else(|| ShellError::NushellFailed {
             msg: "Tried to call an EngineInterface method that requires a call context \
@@ -432,8 +899,35 @@ impl EngineInterface {
                     Some(writer),
                 )
             }
+            EngineCall::CallDecl {
+                decl_id,
+                call,
+                input,
+                redirect_stdout,
+                redirect_stderr,
+            } => {
+                let (header, writer) = self.init_write_pipeline_data(input)?;
+                (
+                    EngineCall::CallDecl {
+                        decl_id,
+                        call,
+                        input: header,
+                        redirect_stdout,
+                        redirect_stderr,
+                    },
+                    Some(writer),
+                )
+            }
             // These calls have no pipeline data, so they're just the same on both sides
             EngineCall::GetConfig => (EngineCall::GetConfig, None),
+            EngineCall::GetEnvVar(name) => (EngineCall::GetEnvVar(name), None),
+            EngineCall::GetEnvVars => (EngineCall::GetEnvVars, None),
+            EngineCall::GetCurrentDir => (EngineCall::GetCurrentDir, None),
+            EngineCall::AddEnvVar(name, value) => (EngineCall::AddEnvVar(name, value), None),
+            EngineCall::FindDecl(name) => (EngineCall::FindDecl(name), None),
+            EngineCall::ReportDiagnostic { severity, msg, span } => {
+                (EngineCall::ReportDiagnostic { severity, msg, span }, None)
+            }
         };
 
         // Register the channel
@@ -454,15 +948,157 @@ impl EngineInterface {
             writer.write_background();
         }
 
-        // Wait on receiver to get the response
-        rx.recv().map_err(|_| ShellError::NushellFailed {
-            msg: "Failed to get response to engine call because the channel was closed".into(),
-        })
+        // Wait on receiver to get the response, polling periodically so we notice a timeout or an
+        // interrupt instead of blocking forever if the engine never replies.
+        const POLL_INTERVAL: Duration = Duration::from_millis(50);
+        let started = Instant::now();
+        loop {
+            let wait = match self.state.call_timeout {
+                Some(timeout) => {
+                    let elapsed = started.elapsed();
+                    if elapsed >= timeout {
+                        // The subscription is left registered here rather than actively torn
+                        // down: there's no channel back to the manager for deregistering one, but
+                        // when the real response does eventually arrive, sending it to our now
+                        // -dropped `rx` just fails and is logged, the same as any other caller
+                        // that hung up early.
+                        return Err(ShellError::GenericError {
+                            error: "Engine call timed out".into(),
+                            msg: format!(
+                                "waited {waited:?} for a response to engine call id={id}, \
+                                    but the engine never replied",
+                                waited = timeout,
+                            ),
+                            span: None,
+                            help: None,
+                            inner: vec![],
+                        });
+                    }
+                    POLL_INTERVAL.min(timeout - elapsed)
+                }
+                None => POLL_INTERVAL,
+            };
+
+            match rx.recv_timeout(wait) {
+                Ok(response) => return Ok(response),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let interrupted = self
+                        .state
+                        .interrupt
+                        .as_ref()
+                        .map(|flag| flag.load(Ordering::Relaxed))
+                        .unwrap_or(false);
+                    if interrupted {
+                        return Err(ShellError::GenericError {
+                            error: "Engine call interrupted".into(),
+                            msg: format!("engine call id={id} was cancelled"),
+                            span: None,
+                            help: None,
+                            inner: vec![],
+                        });
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err(ShellError::NushellFailed {
+                        msg: "Failed to get response to engine call because the channel was \
+                            closed"
+                            .into(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Ask the engine for the value of an environment variable in the caller's scope.
+    ///
+    /// Returns `None` if the variable is not set, distinguishing that from a variable that's set
+    /// to an empty string. Internally, the engine reports a missing variable with
+    /// `EngineCallError::NotFound`, which this collapses to `None` for convenience.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use nu_protocol::{Value, ShellError};
+    /// # use nu_plugin::EngineInterface;
+    /// # fn example(engine: &EngineInterface) -> Result<(), ShellError> {
+    /// if let Some(path) = engine.get_env_var("PATH")? {
+    ///     eprintln!("PATH = {}", path.into_string()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_env_var(&self, name: impl Into<String>) -> Result<Option<Value>, ShellError> {
+        match self.engine_call(EngineCall::GetEnvVar(name.into()))? {
+            EngineCallResponse::PipelineData(data) => Ok(Some(data.into_value(Span::unknown()))),
+            EngineCallResponse::Error(ShellError::EngineCallFailed(
+                EngineCallError::NotFound { .. },
+            )) => Ok(None),
+            EngineCallResponse::Error(err) => Err(err),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response for EngineCall::GetEnvVar".into(),
+            }),
+        }
+    }
+
+    /// Ask the engine for all environment variables visible in the caller's scope, as a single
+    /// record value, rather than having to ask for each one individually by name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use nu_protocol::{Value, ShellError};
+    /// # use nu_plugin::EngineInterface;
+    /// # fn example(engine: &EngineInterface) -> Result<(), ShellError> {
+    /// for (name, value) in engine.get_env_vars()?.into_record()? {
+    ///     eprintln!("{name} = {}", value.into_string()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_env_vars(&self) -> Result<Value, ShellError> {
+        match self.engine_call(EngineCall::GetEnvVars)? {
+            EngineCallResponse::PipelineData(data) => Ok(data.into_value(Span::unknown())),
+            EngineCallResponse::Error(err) => Err(err),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response for EngineCall::GetEnvVars".into(),
+            }),
+        }
+    }
+
+    /// Ask the engine for the caller's current working directory.
+    pub fn get_current_dir(&self) -> Result<String, ShellError> {
+        match self.engine_call(EngineCall::GetCurrentDir)? {
+            EngineCallResponse::PipelineData(data) => {
+                data.into_value(Span::unknown()).into_string()
+            }
+            EngineCallResponse::Error(err) => Err(err),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response for EngineCall::GetCurrentDir".into(),
+            }),
+        }
+    }
+
+    /// Ask the engine to set an environment variable in the caller's scope.
+    ///
+    /// The change is only visible after the plugin call returns, since environment updates are
+    /// applied to the caller's `Stack` rather than propagated live.
+    pub fn add_env_var(&self, name: impl Into<String>, value: Value) -> Result<(), ShellError> {
+        match self.engine_call(EngineCall::AddEnvVar(name.into(), value))? {
+            EngineCallResponse::PipelineData(_) => Ok(()),
+            EngineCallResponse::Error(err) => Err(err),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response for EngineCall::AddEnvVar".into(),
+            }),
+        }
     }
 
     /// Get the full shell configuration from the engine. As this is quite a large object, it is
     /// provided on request only.
     ///
+    /// Called outside of a command invocation, this fails with
+    /// `ShellError::EngineCallFailed(EngineCallError::OutsideInvocation { .. })`, matchable rather
+    /// than just a formatted message.
+    ///
     /// # Example
     ///
     /// Format a value in the user's preferred way:
@@ -531,6 +1167,11 @@ impl EngineInterface {
     /// Closure says: Hello, 3
     /// Closure says: Hello, 4
     /// ```
+    ///
+    /// If the closure itself fails to evaluate, the error is
+    /// `ShellError::EngineCallFailed(EngineCallError::EvalFailed(_))`, wrapping the error the
+    /// engine's evaluator produced; called outside of a command invocation, it's
+    /// `EngineCallError::OutsideInvocation` instead.
     pub fn eval_closure_with_stream(
         &self,
         closure: &Spanned<Closure>,
@@ -610,6 +1251,103 @@ impl EngineInterface {
             value => Ok(value),
         }
     }
+
+    /// Look up a registered command or alias by name, for later use with
+    /// [`call_decl_with_stream()`](Self::call_decl_with_stream)/[`call_decl()`](Self::call_decl).
+    /// This lets a plugin compose with the rest of the shell - e.g. calling `from json` or a
+    /// user-defined command - instead of being limited to evaluating closures it was handed.
+    ///
+    /// Fails with `ShellError::EngineCallFailed(EngineCallError::NotFound { .. })` if no command
+    /// or alias by that name is registered.
+    pub fn find_decl(&self, name: impl Into<String>) -> Result<DeclId, ShellError> {
+        match self.engine_call(EngineCall::FindDecl(name.into()))? {
+            EngineCallResponse::DeclId(decl_id) => Ok(decl_id),
+            EngineCallResponse::Error(err) => Err(err),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response for EngineCall::FindDecl".into(),
+            }),
+        }
+    }
+
+    /// Call a command or alias previously looked up with [`find_decl()`](Self::find_decl),
+    /// exactly as if it had been invoked from nushell source. Input to the command is passed as a
+    /// stream, and the output is available as a stream.
+    ///
+    /// Set `redirect_stdout`/`redirect_stderr` the same way as for
+    /// [`eval_closure_with_stream()`](Self::eval_closure_with_stream).
+    pub fn call_decl_with_stream(
+        &self,
+        decl_id: DeclId,
+        call: EvaluatedCall,
+        input: PipelineData,
+        redirect_stdout: bool,
+        redirect_stderr: bool,
+    ) -> Result<PipelineData, ShellError> {
+        let call = EngineCall::CallDecl {
+            decl_id,
+            call,
+            input,
+            redirect_stdout,
+            redirect_stderr,
+        };
+
+        match self.engine_call(call)? {
+            EngineCallResponse::Error(error) => Err(error),
+            EngineCallResponse::PipelineData(data) => Ok(data),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response type for EngineCall::CallDecl".into(),
+            }),
+        }
+    }
+
+    /// Call a command or alias previously looked up with [`find_decl()`](Self::find_decl). Input
+    /// is optionally passed as a [`Value`], and output is collected to a [`Value`] even if it is
+    /// a stream.
+    ///
+    /// Use [`call_decl_with_stream()`](Self::call_decl_with_stream) if more control over the
+    /// input and output is desired.
+    pub fn call_decl(
+        &self,
+        decl_id: DeclId,
+        call: EvaluatedCall,
+        input: Option<Value>,
+    ) -> Result<Value, ShellError> {
+        let span = call.head;
+        let input = input.map_or_else(|| PipelineData::Empty, |v| PipelineData::Value(v, None));
+        let output = self.call_decl_with_stream(decl_id, call, input, true, false)?;
+        match output.into_value(span) {
+            Value::Error { error, .. } => Err(*error),
+            value => Ok(value),
+        }
+    }
+
+    /// Report a non-fatal diagnostic to the engine, at the given `severity`, optionally pointing
+    /// at a `span` in the caller's source. This lets a long-running plugin surface many warnings
+    /// or errors as it works instead of aborting on the first one or printing ad-hoc text to
+    /// stderr - the engine collects them and prints a final "N warnings, M errors" tally, with
+    /// the diagnostics themselves, once the call completes.
+    ///
+    /// Returns the running total for this call, in case the plugin wants to report its own
+    /// progress without keeping a separate count.
+    pub fn report_diagnostic(
+        &self,
+        severity: JsonDiagnosticLevel,
+        msg: impl Into<String>,
+        span: Option<Span>,
+    ) -> Result<DiagnosticSummary, ShellError> {
+        let call = EngineCall::ReportDiagnostic {
+            severity,
+            msg: msg.into(),
+            span,
+        };
+        match self.engine_call(call)? {
+            EngineCallResponse::DiagnosticSummary(summary) => Ok(summary),
+            EngineCallResponse::Error(err) => Err(err),
+            _ => Err(ShellError::PluginFailedToDecode {
+                msg: "Received unexpected response for EngineCall::ReportDiagnostic".into(),
+            }),
+        }
+    }
 }
 
 impl Interface for EngineInterface {
@@ -639,16 +1377,84 @@ impl Interface for EngineInterface {
                 let value = PluginCustomValue::serialize_custom_values_in(value)?;
                 Ok(PipelineData::Value(value, meta))
             }
-            PipelineData::ListStream(ListStream { stream, ctrlc, .. }, meta) => Ok(stream
-                .map(|value| {
-                    let span = value.span();
-                    match PluginCustomValue::serialize_custom_values_in(value) {
-                        Ok(value) => value,
-                        Err(err) => Value::error(err, span),
-                    }
+            PipelineData::ListStream(ListStream { stream, ctrlc, .. }, meta) => {
+                let parallel = self.state.parallel_serialize;
+                Ok(map_custom_values(stream, parallel, |value| {
+                    PluginCustomValue::serialize_custom_values_in(value)
                 })
-                .into_pipeline_data_with_metadata(meta, ctrlc)),
+                .into_pipeline_data_with_metadata(meta, ctrlc))
+            }
             PipelineData::Empty | PipelineData::ExternalStream { .. } => Ok(data),
         }
     }
 }
+
+/// A serializable mirror of [`nu_protocol::DataSource`], for sending a stream's data source
+/// across the plugin wire alongside its pipeline data header.
+///
+/// `PipelineMetadata`/`DataSource` aren't `Serialize`/`Deserialize` themselves - they live in
+/// `nu-protocol` and have no reason to know about the plugin wire format - so this mirrors their
+/// shape on this side of the boundary instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PluginDataSource {
+    Ls,
+    HtmlThemes,
+    FilePath(PathBuf),
+}
+
+/// A serializable mirror of [`nu_protocol::PipelineMetadata`]. See [`PluginDataSource`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginMetadata {
+    pub data_source: PluginDataSource,
+    pub content_type: Option<String>,
+}
+
+impl PluginMetadata {
+    /// Mirror a [`PipelineMetadata`] into its serializable form for the wire.
+    ///
+    /// Call sites: this is what the header construction for a plugin call or response would use
+    /// to carry `PipelineData::metadata()` across, and the inverse (`into_pipeline_metadata`) is
+    /// what reading a header back would use to reconstruct it on the other side. Both of those
+    /// live on the call/response and interface-trait definitions, which aren't present in this
+    /// copy of the crate, so nothing calls these yet - they're here so that wiring only has to
+    /// thread a `PluginMetadata` field through, not invent one.
+    pub fn from_pipeline_metadata(metadata: PipelineMetadata) -> Self {
+        PluginMetadata {
+            data_source: match metadata.data_source {
+                DataSource::Ls => PluginDataSource::Ls,
+                DataSource::HtmlThemes => PluginDataSource::HtmlThemes,
+                DataSource::FilePath(path) => PluginDataSource::FilePath(path),
+            },
+            content_type: metadata.content_type,
+        }
+    }
+
+    /// Reconstruct the [`PipelineMetadata`] this mirrors.
+    pub fn into_pipeline_metadata(self) -> PipelineMetadata {
+        PipelineMetadata {
+            data_source: match self.data_source {
+                PluginDataSource::Ls => DataSource::Ls,
+                PluginDataSource::HtmlThemes => DataSource::HtmlThemes,
+                PluginDataSource::FilePath(path) => DataSource::FilePath(path),
+            },
+            content_type: self.content_type,
+        }
+    }
+}
+
+/// Attach `content_type` to `data`'s existing metadata, for a plugin command to tag the shape of
+/// the output it returns (e.g. `application/json`) before handing it back to the engine.
+///
+/// If `data` doesn't already carry a [`PipelineMetadata`] (i.e. [`PipelineData::metadata`]
+/// returns `None`), it's left untouched rather than inventing one here: `PipelineMetadata`
+/// requires a concrete [`DataSource`], and a plugin output that didn't come from a file or `ls`
+/// has no such source to report, only a content type with nothing to hang it off of.
+pub fn tag_content_type(data: PipelineData, content_type: impl Into<String>) -> PipelineData {
+    match data.metadata() {
+        Some(mut metadata) => {
+            metadata.content_type = Some(content_type.into());
+            data.set_metadata(Some(metadata))
+        }
+        None => data,
+    }
+}
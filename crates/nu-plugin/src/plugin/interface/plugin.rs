@@ -1,8 +1,13 @@
 //! Interface used by the engine to communicate with the plugin.
 
 use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
-use nu_protocol::{PipelineData, PluginSignature, ShellError, Value, ListStream, IntoInterruptiblePipelineData, Spanned};
+use nu_protocol::{
+    engine::JsonDiagnosticLevel, PipelineData, PluginSignature, ShellError, Value, ListStream,
+    IntoInterruptiblePipelineData, IntoPipelineData, Spanned, Span,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     plugin::{context::PluginExecutionContext, PluginIdentity},
@@ -69,6 +74,13 @@ struct PluginInterfaceState {
     contexts: Mutex<Vec<(PluginCallId, Context)>>,
     /// The synchronized output writer
     writer: Box<dyn PluginWrite<PluginInput>>,
+    /// How long to wait for a response to a plugin call before giving up on it. `None` (the
+    /// default) preserves the old wait-forever behavior.
+    call_timeout: Option<Duration>,
+    /// Ordered chain of [`EngineCallMiddleware`] run over every [`EngineCall`] the plugin makes,
+    /// before it reaches [`handle_engine_call`]. Empty by default, so engine calls dispatch
+    /// exactly as they did before middleware existed.
+    engine_call_middleware: Vec<Arc<dyn EngineCallMiddleware>>,
 }
 
 impl std::fmt::Debug for PluginInterfaceState {
@@ -137,6 +149,243 @@ pub(crate) struct PluginInterfaceManager {
     stream_manager: StreamManager,
     /// Protocol version info, set after `Hello` received
     protocol_info: Option<ProtocolInfo>,
+    /// The encoding chosen during the `Hello` handshake, picked as the first mutually supported
+    /// entry in `ProtocolInfo::encodings`, in our own preference order
+    negotiated_encoding: Option<EncodingType>,
+    /// The transport chosen during the `Hello` handshake, picked as the first mutually supported
+    /// entry in `ProtocolInfo::transports`, in our own preference order. `Some("stdio")` when
+    /// stdio is the best transport both sides offer; `None` before `Hello` arrives, or when the
+    /// plugin offered no transport we support.
+    negotiated_transport: Option<String>,
+    /// Optional features supported by both us and the plugin, computed as the intersection of
+    /// what each side advertised in `Hello`. See the matching field in `engine.rs` for the plugin
+    /// side of this negotiation.
+    negotiated_features: Vec<Feature>,
+    /// The codec chosen to compress stream bodies with, if [`Feature::StreamCompression`] was
+    /// negotiated and neither side disabled it. `None` means no codec was picked, either because
+    /// compression wasn't negotiated or because [`Self::compression_override`] forced it off.
+    /// Only the choice is recorded here; stream bodies are not compressed with it. Control messages (`Call`, `EngineCall`, `CallResponse`, ...) are never
+    /// compressed regardless of this setting.
+    negotiated_compression: Option<CompressionCodec>,
+    /// A user-configured override applied instead of the usual preference-order pick:
+    /// `Some(Some(codec))` forces that codec if the plugin also supports it, `Some(None)` forces
+    /// compression off entirely, and `None` (the default) negotiates normally. Set with
+    /// [`PluginInterfaceManager::set_compression_override`] before `Hello` is exchanged.
+    compression_override: Option<Option<CompressionCodec>>,
+}
+
+/// A wire encoding the plugin protocol can be serialized with. `MsgPack` is preferred for binary
+/// values and large tables since it avoids the base64/escaping overhead `Json` needs for binary
+/// data; `Json` remains available as the compatibility fallback for plugins that only implement
+/// it. `Bincode` is an additional, faster option for plugins written against this engine
+/// specifically, since it isn't a format other implementations are expected to support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EncodingType {
+    Bincode,
+    MsgPack,
+    Json,
+}
+
+impl EncodingType {
+    fn as_str(self) -> &'static str {
+        match self {
+            EncodingType::Bincode => "bincode",
+            EncodingType::MsgPack => "msgpack",
+            EncodingType::Json => "json",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<EncodingType> {
+        match name {
+            "bincode" => Some(EncodingType::Bincode),
+            "msgpack" => Some(EncodingType::MsgPack),
+            "json" => Some(EncodingType::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Our preferred encodings, most preferred first.
+const SUPPORTED_ENCODINGS: &[EncodingType] =
+    &[EncodingType::Bincode, EncodingType::MsgPack, EncodingType::Json];
+
+/// Pick the best mutually supported encoding, in our own preference order.
+fn negotiate_encoding(remote_encodings: &[String]) -> Option<EncodingType> {
+    SUPPORTED_ENCODINGS.iter().copied().find(|ours| {
+        remote_encodings
+            .iter()
+            .any(|theirs| EncodingType::from_str(theirs) == Some(*ours))
+    })
+}
+
+/// Our preferred transports, most preferred first. `local_socket` (a Unix domain socket, or a
+/// named pipe on Windows) avoids going through the engine's own stdio, so a plugin's accidental
+/// writes to stdout don't get mistaken for protocol messages; `stdio` is the universal fallback
+/// for plugins that don't support connecting back out to a socket.
+const SUPPORTED_TRANSPORTS: &[&str] = &["local_socket", "stdio"];
+
+/// Pick the best mutually supported transport, in our own preference order. Mirrors
+/// [`negotiate_encoding`], but for the channel the protocol itself is carried over rather than
+/// the encoding used to serialize messages on it.
+///
+/// Wiring the chosen transport into an actual connection - binding the local socket, passing its
+/// address to the plugin process, and accepting the connection instead of using the child's
+/// stdio pipes - happens where the plugin process is spawned, which isn't part of this module.
+fn negotiate_transport(remote_transports: &[String]) -> Option<&'static str> {
+    SUPPORTED_TRANSPORTS
+        .iter()
+        .copied()
+        .find(|ours| remote_transports.iter().any(|theirs| theirs == ours))
+}
+
+/// An optional behavior that isn't required for the protocol to function. See the matching type
+/// in `engine.rs` for the plugin side of this negotiation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Feature {
+    LocalSocket,
+    StreamCompression,
+}
+
+impl Feature {
+    fn from_str(name: &str) -> Option<Feature> {
+        match name {
+            "local_socket" => Some(Feature::LocalSocket),
+            "stream_compression" => Some(Feature::StreamCompression),
+            _ => None,
+        }
+    }
+}
+
+/// Optional features we implement. Mirrors `SUPPORTED_FEATURES` in `engine.rs`.
+const SUPPORTED_FEATURES: &[Feature] = &[Feature::LocalSocket, Feature::StreamCompression];
+
+/// A codec stream bodies can be compressed with when [`Feature::StreamCompression`] is
+/// negotiated. Only ever applied to stream bodies (`Data`/`Stream` messages) - control messages
+/// stay uncompressed, since they're small and latency-sensitive, and compressing them would cost
+/// more in overhead than it would ever save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CompressionCodec {
+    /// Best ratio-for-CPU tradeoff for the row-oriented data plugins typically stream; preferred
+    /// whenever both sides support it.
+    Zstd,
+    /// Kept as a fallback for plugins built against an older compression library.
+    Bzip2,
+}
+
+impl CompressionCodec {
+    fn as_str(self) -> &'static str {
+        match self {
+            CompressionCodec::Zstd => "zstd",
+            CompressionCodec::Bzip2 => "bzip2",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<CompressionCodec> {
+        match name {
+            "zstd" => Some(CompressionCodec::Zstd),
+            "bzip2" => Some(CompressionCodec::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+/// Our preferred compression codecs, most preferred first.
+const SUPPORTED_COMPRESSIONS: &[CompressionCodec] =
+    &[CompressionCodec::Zstd, CompressionCodec::Bzip2];
+
+/// Pick the compression codec to use for stream bodies, honoring `override_` over the usual
+/// preference-order negotiation. Mirrors [`negotiate_encoding`], except the result can be `None`
+/// even when both sides are capable, since compression itself (unlike the wire encoding) is
+/// optional.
+fn negotiate_compression(
+    remote_compressions: &[String],
+    override_: Option<Option<CompressionCodec>>,
+) -> Option<CompressionCodec> {
+    if let Some(forced) = override_ {
+        return forced.filter(|codec| {
+            remote_compressions
+                .iter()
+                .any(|theirs| CompressionCodec::from_str(theirs) == Some(*codec))
+        });
+    }
+    SUPPORTED_COMPRESSIONS.iter().copied().find(|ours| {
+        remote_compressions
+            .iter()
+            .any(|theirs| CompressionCodec::from_str(theirs) == Some(*ours))
+    })
+}
+
+/// Compute the features usable this session, ignoring any feature string neither side recognizes
+/// rather than rejecting the handshake over it. Mirrors `negotiate_features` in `engine.rs`.
+fn negotiate_features(remote_features: &[String]) -> Vec<Feature> {
+    SUPPORTED_FEATURES
+        .iter()
+        .copied()
+        .filter(|ours| {
+            remote_features
+                .iter()
+                .any(|theirs| Feature::from_str(theirs) == Some(*ours))
+        })
+        .collect()
+}
+
+/// A connected channel to a plugin, wrapping either its own stdio pipes or a persistent
+/// connection to a socket it's listening on. Exists so that whatever spawns the plugin process
+/// can hand either kind of channel to [`PluginInterfaceManager::new`] and
+/// [`PluginInterfaceManager::consume_all`] through one type, rather than those functions needing
+/// a separate code path per transport.
+///
+/// Picking *which* variant to build - binding a local socket listener, passing its address to the
+/// plugin, and accepting the connection instead of wiring up the child's stdio pipes - happens
+/// wherever the process is spawned, which isn't part of this module; see
+/// [`PluginInterfaceManager::negotiated_transport`] for the hook that decision is based on. A
+/// `LocalSocket` connection is expected to be held open and handed to [`PluginInterfaceManager`]
+/// again for subsequent `run` calls, so the plugin can stay resident instead of being respawned
+/// each time; `Stdio` is torn down along with the child process after each call, same as today.
+///
+/// Despite the name, `LocalSocket` isn't limited to same-host connections: a TCP or TLS stream to
+/// a plugin running on another machine fits the same shape (a single bidirectional byte stream
+/// handed to both `PluginWrite` and `PluginRead`), so a remote plugin uses this variant too. The
+/// `PluginIdentity` the connection was established against (local path or network endpoint) is
+/// what `PluginCustomValue::add_source`/`verify_source` tag values with, so round-tripping a
+/// custom value works the same way regardless of which kind of connection it crossed.
+#[derive(Debug)]
+pub(crate) enum PluginTransport<Stdio, Socket> {
+    Stdio(Stdio),
+    LocalSocket(Socket),
+}
+
+impl<Stdio, Socket> PluginRead<PluginOutput> for PluginTransport<Stdio, Socket>
+where
+    Stdio: PluginRead<PluginOutput>,
+    Socket: PluginRead<PluginOutput>,
+{
+    fn read(&mut self) -> Result<Option<PluginOutput>, ShellError> {
+        match self {
+            PluginTransport::Stdio(reader) => reader.read(),
+            PluginTransport::LocalSocket(reader) => reader.read(),
+        }
+    }
+}
+
+impl<Stdio, Socket> PluginWrite<PluginInput> for PluginTransport<Stdio, Socket>
+where
+    Stdio: PluginWrite<PluginInput>,
+    Socket: PluginWrite<PluginInput>,
+{
+    fn write(&self, input: &PluginInput) -> Result<(), ShellError> {
+        match self {
+            PluginTransport::Stdio(writer) => writer.write(input),
+            PluginTransport::LocalSocket(writer) => writer.write(input),
+        }
+    }
+
+    fn flush(&self) -> Result<(), ShellError> {
+        match self {
+            PluginTransport::Stdio(writer) => writer.flush(),
+            PluginTransport::LocalSocket(writer) => writer.flush(),
+        }
+    }
 }
 
 impl PluginInterfaceManager {
@@ -152,9 +401,19 @@ impl PluginInterfaceManager {
                 plugin_call_response_senders: Mutex::new(Vec::new()),
                 contexts: Mutex::new(Vec::new()),
                 writer: Box::new(writer),
+                // Opt-in, like the stream manager's keepalive; `None` preserves the old
+                // wait-forever behavior.
+                call_timeout: None,
+                engine_call_middleware: Vec::new(),
             }),
-            stream_manager: StreamManager::new(),
+            // Keepalive is opt-in; `None` preserves the old wait-forever behavior.
+            stream_manager: StreamManager::new(None),
             protocol_info: None,
+            negotiated_encoding: None,
+            negotiated_transport: None,
+            negotiated_features: Vec::new(),
+            negotiated_compression: None,
+            compression_override: None,
         }
     }
 
@@ -245,6 +504,66 @@ impl PluginInterfaceManager {
         Arc::strong_count(&self.state) < 2
     }
 
+    /// The transport negotiated with the plugin during the `Hello` handshake. Whoever spawned the
+    /// plugin process can use this after
+    /// the handshake completes to decide whether to open a [`PluginTransport::LocalSocket`]
+    /// connection for the *next* call instead of respawning the plugin. Returns `Some("stdio")`
+    /// if stdio is all this plugin and this engine have in common, and `None` before `Hello`
+    /// arrives or if they share no transport at all.
+    pub(crate) fn negotiated_transport(&self) -> Option<&str> {
+        self.negotiated_transport.as_deref()
+    }
+
+    /// True if [`Feature::LocalSocket`] was negotiated, i.e. this plugin is eligible to be kept
+    /// resident across multiple `run` calls over a [`PluginTransport::LocalSocket`] connection
+    /// instead of being respawned for each one.
+    pub(crate) fn supports_persistent_socket(&self) -> bool {
+        self.negotiated_features.contains(&Feature::LocalSocket)
+    }
+
+    /// The codec, if any, negotiated for compressing stream bodies on this connection. Nothing
+    /// in this crate applies it: stream bodies are sent uncompressed whether or not one was
+    /// negotiated.
+    pub(crate) fn negotiated_compression(&self) -> Option<CompressionCodec> {
+        self.negotiated_compression
+    }
+
+    /// Force a particular stream compression codec, or force compression off, instead of letting
+    /// the `Hello` handshake pick one automatically. Must be called before `Hello` is exchanged;
+    /// has no effect afterward, since negotiation has already happened by then.
+    ///
+    /// - `Some(Some(codec))` uses `codec` if the plugin also supports it, and falls back to no
+    ///   compression if it doesn't.
+    /// - `Some(None)` disables compression entirely, even if both sides support it.
+    /// - `None` (the default) negotiates normally, preferring [`SUPPORTED_COMPRESSIONS`] in order.
+    pub(crate) fn set_compression_override(&mut self, override_: Option<Option<CompressionCodec>>) {
+        self.compression_override = override_;
+    }
+
+    /// Append a stage to the [`EngineCallMiddleware`] chain run over every engine call this
+    /// plugin makes. Stages run in registration order. The chain lives in the
+    /// [`PluginInterfaceState`] that every [`PluginInterface`] shares, so a stage can only be
+    /// added before the first interface is handed out (e.g. by `get_interface`); after that this
+    /// does nothing and the stage is not added.
+    pub(crate) fn add_engine_call_middleware(&mut self, middleware: Arc<dyn EngineCallMiddleware>) {
+        if let Some(state) = Arc::get_mut(&mut self.state) {
+            state.engine_call_middleware.push(middleware);
+        }
+    }
+
+    /// Set how long to wait for a response to a plugin call before giving up on it and returning
+    /// [`ShellError::PluginCallTimeout`]. `None` disables the timeout and waits forever, which is
+    /// the default. Applies to every call made through [`PluginInterface::plugin_call`] -
+    /// `run()`, `get_signature()`, and `custom_value_to_base_value()` alike, since they're all
+    /// implemented in terms of it.
+    pub(crate) fn set_call_timeout(&mut self, call_timeout: Option<Duration>) {
+        // `state` is shared via `Arc`, but this is only ever called right after construction,
+        // before any `PluginInterface` clones exist to race with.
+        if let Some(state) = Arc::get_mut(&mut self.state) {
+            state.call_timeout = call_timeout;
+        }
+    }
+
     /// Loop on input from the given reader as long as `is_finished()` is false
     ///
     /// Any errors will be propagated to all read streams automatically.
@@ -291,6 +610,34 @@ impl InterfaceManager for PluginInterfaceManager {
             PluginOutput::Hello(info) => {
                 let local_info = ProtocolInfo::default();
                 if local_info.is_compatible_with(&info)? {
+                    self.negotiated_encoding = Some(negotiate_encoding(&info.encodings).ok_or_else(|| {
+                        ShellError::PluginFailedToLoad {
+                            msg: format!(
+                                "Plugin {} offered no encoding we support ({:?}); \
+                                    we support {:?}",
+                                self.state.identity.name(),
+                                info.encodings,
+                                SUPPORTED_ENCODINGS
+                                    .iter()
+                                    .map(|e| e.as_str())
+                                    .collect::<Vec<_>>(),
+                            ),
+                        }
+                    })?);
+                    // Note: a negotiated transport here only records what both sides *can*
+                    // speak. This connection is already established over whatever transport it
+                    // was opened on, so there's nothing to switch to mid-handshake - a future
+                    // connection attempt to this same plugin is what would actually use it.
+                    self.negotiated_transport =
+                        negotiate_transport(&info.transports).map(Into::into);
+                    self.negotiated_features = negotiate_features(&info.features);
+                    // Only worth negotiating a codec at all if both sides advertised the
+                    // feature; otherwise the plugin has no decompressor waiting on the other end.
+                    self.negotiated_compression = self
+                        .negotiated_features
+                        .contains(&Feature::StreamCompression)
+                        .then(|| negotiate_compression(&info.compressions, self.compression_override))
+                        .flatten();
                     self.protocol_info = Some(info);
                     Ok(())
                 } else {
@@ -337,6 +684,12 @@ impl InterfaceManager for PluginInterfaceManager {
                 let ctrlc = exec_context.as_ref().and_then(|c| c.0.ctrlc());
                 let call = match call {
                     EngineCall::GetConfig => Ok(EngineCall::GetConfig),
+                    EngineCall::GetEnvVar(name) => Ok(EngineCall::GetEnvVar(name)),
+                    EngineCall::GetEnvVars => Ok(EngineCall::GetEnvVars),
+                    EngineCall::GetCurrentDir => Ok(EngineCall::GetCurrentDir),
+                    EngineCall::AddEnvVar(name, value) => {
+                        Ok(EngineCall::AddEnvVar(name, value))
+                    }
                     EngineCall::EvalClosure {
                         closure,
                         positional,
@@ -352,6 +705,25 @@ impl InterfaceManager for PluginInterfaceManager {
                             redirect_stderr,
                         }
                     }),
+                    EngineCall::FindDecl(name) => Ok(EngineCall::FindDecl(name)),
+                    EngineCall::CallDecl {
+                        decl_id,
+                        call,
+                        input,
+                        redirect_stdout,
+                        redirect_stderr,
+                    } => self.read_pipeline_data(input, ctrlc).map(|input| {
+                        EngineCall::CallDecl {
+                            decl_id,
+                            call,
+                            input,
+                            redirect_stdout,
+                            redirect_stderr,
+                        }
+                    }),
+                    EngineCall::ReportDiagnostic { severity, msg, span } => {
+                        Ok(EngineCall::ReportDiagnostic { severity, msg, span })
+                    }
                 };
                 match call {
                     Ok(call) => self.send_engine_call(context, id, call),
@@ -420,6 +792,10 @@ impl PluginInterface {
             // No pipeline data:
             EngineCallResponse::Error(err) => (EngineCallResponse::Error(err), None),
             EngineCallResponse::Config(config) => (EngineCallResponse::Config(config), None),
+            EngineCallResponse::DeclId(decl_id) => (EngineCallResponse::DeclId(decl_id), None),
+            EngineCallResponse::DiagnosticSummary(summary) => {
+                (EngineCallResponse::DiagnosticSummary(summary), None)
+            }
         };
 
         // Write the response, including the pipeline data header if present
@@ -488,8 +864,32 @@ impl PluginInterface {
             writer.write_background();
         }
 
+        // Once `call_timeout` elapses without a final response, we give up rather than block the
+        // calling thread on a hung plugin forever. Engine calls in between still reset nothing -
+        // the budget covers the whole call, not just the wait for the *next* message - since a
+        // plugin that keeps the conversation alive with engine calls just to stall is exactly the
+        // kind of misbehavior this is meant to catch.
+        let deadline = self.state.call_timeout.map(|timeout| Instant::now() + timeout);
+
         // Handle messages from receiver
-        for msg in rx {
+        loop {
+            let msg = match deadline {
+                Some(deadline) => {
+                    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                        Ok(msg) => msg,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            return self.cancel_plugin_call(id, self.state.call_timeout.expect(
+                                "deadline is only set when call_timeout is Some",
+                            ));
+                        }
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match rx.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+            };
             match msg {
                 ReceivedPluginCallMessage::Response(resp) => {
                     return Ok(resp);
@@ -498,8 +898,14 @@ impl PluginInterface {
                     return Err(err);
                 }
                 ReceivedPluginCallMessage::EngineCall(engine_call_id, engine_call) => {
-                    let resp = handle_engine_call(engine_call, context)
-                        .unwrap_or_else(EngineCallResponse::Error);
+                    let resp = match self.run_engine_call_middleware(id, engine_call) {
+                        EngineCallDecision::Allow(engine_call) => {
+                            handle_engine_call(engine_call, context)
+                                .unwrap_or_else(EngineCallResponse::Error)
+                        }
+                        EngineCallDecision::Deny(err) => EngineCallResponse::Error(err),
+                        EngineCallDecision::Respond(resp) => resp,
+                    };
                     // Handle stream
                     let (resp, writer) = match resp {
                         EngineCallResponse::Error(error) => {
@@ -508,6 +914,12 @@ impl PluginInterface {
                         EngineCallResponse::Config(config) => {
                             (EngineCallResponse::Config(config), None)
                         }
+                        EngineCallResponse::DeclId(decl_id) => {
+                            (EngineCallResponse::DeclId(decl_id), None)
+                        }
+                        EngineCallResponse::DiagnosticSummary(summary) => {
+                            (EngineCallResponse::DiagnosticSummary(summary), None)
+                        }
                         EngineCallResponse::PipelineData(data) => {
                             match self.init_write_pipeline_data(data) {
                                 Ok((header, writer)) => {
@@ -533,7 +945,62 @@ impl PluginInterface {
         })
     }
 
+    /// Thread `call` through every registered [`EngineCallMiddleware`] stage in order, stopping
+    /// as soon as one denies the call or short-circuits with its own response. Stages that allow
+    /// the call hand their (possibly transformed) `EngineCall` on to the next stage; if every
+    /// stage allows it, the final `EngineCall` is returned as an `Allow` for the caller to
+    /// dispatch.
+    fn run_engine_call_middleware(
+        &self,
+        plugin_call_id: PluginCallId,
+        call: EngineCall<PipelineData>,
+    ) -> EngineCallDecision {
+        if self.state.identity.is_remote() {
+            if let Err(err) = engine_call_supported_remotely(&call) {
+                return EngineCallDecision::Deny(err);
+            }
+        }
+
+        let mut call = call;
+        for middleware in &self.state.engine_call_middleware {
+            match middleware.before_dispatch(plugin_call_id, &self.state.identity, call) {
+                EngineCallDecision::Allow(next_call) => call = next_call,
+                decision => return decision,
+            }
+        }
+        EngineCallDecision::Allow(call)
+    }
+
+    /// Give up on a plugin call that's been waiting longer than `call_timeout`: forget the
+    /// sender and context registered for `id` (the same bookkeeping
+    /// [`send_plugin_call_response`](PluginInterfaceManager::send_plugin_call_response) would
+    /// have done on a normal response), let the plugin know so it can abort any in-flight stream
+    /// writes tied to this call, and report [`ShellError::PluginCallTimeout`].
+    fn cancel_plugin_call(
+        &self,
+        id: PluginCallId,
+        duration: Duration,
+    ) -> Result<PluginCallResponse<PipelineData>, ShellError> {
+        if let Ok(mut senders) = self.state.lock_plugin_call_response_senders() {
+            if let Some(index) = senders.iter().position(|(sender_id, _)| *sender_id == id) {
+                senders.swap_remove(index);
+            }
+        }
+        let _ = self.state.remove_context(id);
+
+        // Best-effort: if we can't tell the plugin to stop, we still give up on our end.
+        if self.write(PluginInput::CallCancellation(id)).is_ok() {
+            let _ = self.flush();
+        }
+
+        Err(ShellError::PluginCallTimeout { duration })
+    }
+
     /// Get the command signatures from the plugin.
+    ///
+    /// This call is idempotent, so a caller that wants to recover from the plugin crashing
+    /// mid-call should go through [`PluginSupervisor::retry_idempotent_call`] rather than calling
+    /// this directly.
     pub(crate) fn get_signature(&self) -> Result<Vec<PluginSignature>, ShellError> {
         match self.plugin_call(PluginCall::Signature, &None)? {
             PluginCallResponse::Signature(sigs) => Ok(sigs),
@@ -561,6 +1028,10 @@ impl PluginInterface {
     }
 
     /// Collapse a custom value to its base value.
+    ///
+    /// This call is idempotent, so a caller that wants to recover from the plugin crashing
+    /// mid-call should go through [`PluginSupervisor::retry_idempotent_call`] rather than calling
+    /// this directly.
     pub(crate) fn custom_value_to_base_value(
         &self,
         value: Spanned<PluginCustomValue>,
@@ -620,6 +1091,105 @@ impl Interface for PluginInterface {
     }
 }
 
+/// A stage in the [`EngineCallMiddleware`] chain registered on a [`PluginInterfaceManager`] via
+/// [`PluginInterfaceManager::add_engine_call_middleware`]. Sees every [`EngineCall`] a plugin
+/// makes, along with the [`PluginCallId`] it's nested under and the [`PluginIdentity`] of the
+/// plugin that made it, before it's dispatched to [`handle_engine_call`]. Can let the call through
+/// unchanged, transform it (e.g. stripping arguments before logging them elsewhere), deny it with
+/// an error, or short-circuit with a response of its own - enough to build sandboxing policies
+/// (deny `EvalClosure` for an untrusted plugin), per-plugin engine-call metrics, or audit logging,
+/// without touching the core dispatch logic in `handle_engine_call`.
+pub(crate) trait EngineCallMiddleware: Send + Sync {
+    fn before_dispatch(
+        &self,
+        plugin_call_id: PluginCallId,
+        identity: &PluginIdentity,
+        call: EngineCall<PipelineData>,
+    ) -> EngineCallDecision;
+}
+
+/// What a single [`EngineCallMiddleware`] stage decided to do with an [`EngineCall`]. Returned by
+/// [`EngineCallMiddleware::before_dispatch`] and consumed by
+/// [`PluginInterface::run_engine_call_middleware`].
+pub(crate) enum EngineCallDecision {
+    /// Let the call continue to the next stage (or, if this was the last one, on to
+    /// `handle_engine_call`), possibly transformed.
+    Allow(EngineCall<PipelineData>),
+    /// Stop here: respond with this error, without running any later stage or dispatching the
+    /// call.
+    Deny(ShellError),
+    /// Stop here: respond with this directly, without running any later stage or dispatching the
+    /// call.
+    Respond(EngineCallResponse<PipelineData>),
+}
+
+/// Reject engine calls that depend on engine-side state a remote plugin has no way to reach.
+/// `EvalClosure` carries a [`Closure`] that only makes sense evaluated against the block table and
+/// variable stack of the engine that created it - neither of which exist on the other end of a
+/// network connection - so it's refused outright rather than forwarded to
+/// [`handle_engine_call`]. Everything else (`GetConfig`, the environment-variable calls,
+/// `GetCurrentDir`) is a plain request/response over serializable data and works the same
+/// regardless of which machine the plugin is running on.
+fn engine_call_supported_remotely(call: &EngineCall<PipelineData>) -> Result<(), ShellError> {
+    match call {
+        EngineCall::EvalClosure { .. } => Err(ShellError::GenericError {
+            error: "Engine call not supported for a remote plugin".into(),
+            msg: "EvalClosure requires engine-side state that isn't reachable over the network"
+                .into(),
+            span: None,
+            help: Some(
+                "plugins running over a remote connection can't ask the engine to evaluate a \
+                closure; move this plugin call to run locally instead"
+                    .into(),
+            ),
+            inner: vec![],
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// A structured reason an [`EngineCall`] could not be completed, carried inside
+/// [`ShellError::EngineCallFailed`] so the plugin can `match` on *why* a call failed instead of
+/// only seeing a formatted message. Not every engine-call failure goes through here - anything
+/// that isn't really about the specific call (a disconnected sender, a poisoned mutex) still
+/// surfaces as a plain [`ShellError`] - but the paths in [`handle_engine_call`] that can name a
+/// specific reason do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EngineCallError {
+    /// The requested item doesn't exist. `kind` names what was being looked up (e.g.
+    /// `"environment variable"` or `"command"`) and `name` is the specific one that was missing.
+    NotFound { kind: String, name: String },
+    /// The call was made outside of a command invocation, so there's no
+    /// [`PluginExecutionContext`] to run it against. `call_name` is the engine call that was
+    /// attempted.
+    OutsideInvocation { call_name: String },
+    /// A closure passed via `EvalClosure` failed to evaluate.
+    EvalFailed(Box<ShellError>),
+}
+
+impl std::fmt::Display for EngineCallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineCallError::NotFound { kind, name } => write!(f, "{kind} not found: {name}"),
+            EngineCallError::OutsideInvocation { call_name } => {
+                write!(f, "attempted to call {call_name} outside of a command invocation")
+            }
+            EngineCallError::EvalFailed(err) => write!(f, "closure evaluation failed: {err}"),
+        }
+    }
+}
+
+/// The accumulated diagnostic counts for a single plugin call, reported back after each
+/// `EngineCall::ReportDiagnostic` so a plugin can see its running total without keeping its own
+/// copy. The buffer these counts are drawn from, and the final "N warnings, M errors emitted by
+/// `<plugin>`" tally printed once the call completes, both live on the engine-side
+/// [`PluginExecutionContext`] implementation, which isn't part of this module.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DiagnosticSummary {
+    pub warnings: u32,
+    pub errors: u32,
+}
+
 /// Handle an engine call.
 pub(crate) fn handle_engine_call(
     call: EngineCall<PipelineData>,
@@ -627,15 +1197,10 @@ pub(crate) fn handle_engine_call(
 ) -> Result<EngineCallResponse<PipelineData>, ShellError> {
     let call_name = call.name();
     let require_context = || {
-        context.as_ref().ok_or_else(|| ShellError::GenericError {
-            error: "A plugin execution context is required for this engine call".into(),
-            msg: format!(
-                "attempted to call {} outside of a command invocation",
-                call_name
-            ),
-            span: None,
-            help: Some("this is probably a bug with the plugin".into()),
-            inner: vec![],
+        context.as_ref().ok_or_else(|| {
+            ShellError::EngineCallFailed(EngineCallError::OutsideInvocation {
+                call_name: call_name.to_string(),
+            })
         })
     };
     match call {
@@ -652,6 +1217,178 @@ pub(crate) fn handle_engine_call(
             redirect_stderr,
         } => require_context()?
             .eval_closure(closure, positional, input, redirect_stdout, redirect_stderr)
+            .map(EngineCallResponse::PipelineData)
+            .map_err(|err| ShellError::EngineCallFailed(EngineCallError::EvalFailed(Box::new(err)))),
+        EngineCall::GetEnvVar(name) => {
+            match require_context()?.get_env_var(&name)? {
+                Some(value) => Ok(EngineCallResponse::PipelineData(value.into_pipeline_data())),
+                None => Err(ShellError::EngineCallFailed(EngineCallError::NotFound {
+                    kind: "environment variable".into(),
+                    name,
+                })),
+            }
+        }
+        EngineCall::AddEnvVar(name, value) => {
+            require_context()?.add_env_var(name, value)?;
+            Ok(EngineCallResponse::PipelineData(PipelineData::Empty))
+        }
+        EngineCall::GetEnvVars => {
+            let vars = require_context()?.get_env_vars()?;
+            Ok(EngineCallResponse::PipelineData(vars.into_pipeline_data()))
+        }
+        EngineCall::GetCurrentDir => {
+            let cwd = require_context()?.get_current_dir()?;
+            Ok(EngineCallResponse::PipelineData(cwd.into_pipeline_data()))
+        }
+        EngineCall::FindDecl(name) => {
+            let context = require_context()?;
+            match context.find_decl(&name)? {
+                Some(decl_id) => Ok(EngineCallResponse::DeclId(decl_id)),
+                None => Err(ShellError::EngineCallFailed(EngineCallError::NotFound {
+                    kind: "command".into(),
+                    name,
+                })),
+            }
+        }
+        EngineCall::CallDecl {
+            decl_id,
+            call,
+            input,
+            redirect_stdout,
+            redirect_stderr,
+        } => require_context()?
+            .call_decl(decl_id, call, input, redirect_stdout, redirect_stderr)
             .map(EngineCallResponse::PipelineData),
+        EngineCall::ReportDiagnostic { severity, msg, span } => {
+            let summary = require_context()?.report_diagnostic(severity, msg, span)?;
+            Ok(EngineCallResponse::DiagnosticSummary(summary))
+        }
+    }
+}
+
+/// Watches a [`PluginInterfaceManager`] for abnormal termination and recovers from it, instead of
+/// leaving every caller blocked in [`PluginInterface::get_signature`] or
+/// [`PluginInterface::custom_value_to_base_value`] to see nothing but an opaque decode error.
+///
+/// Detecting a crash: [`PluginInterfaceManager::consume_all`] returning `Err` while
+/// [`PluginInterfaceManager::is_finished`] is still `false` means the reader hit EOF or a decode
+/// error before anything asked the manager to shut down - a clean shutdown always drops every
+/// [`PluginInterface`] first, which `is_finished` would already reflect by the time `consume_all`
+/// returns.
+///
+/// Recovering from a crash: only plugins eligible for
+/// [`PluginInterfaceManager::supports_persistent_socket`] are worth respawning automatically,
+/// since a stdio-only plugin's transport dies along with its process - there's no connection left
+/// to retry against without building a whole new interface anyway. Idempotent calls in flight at
+/// the time (`Signature`, `CustomValueOp::ToBaseValue`) are replayed against the freshly respawned
+/// interface once `Hello` completes, up to `max_restarts` attempts; `Run` calls are never
+/// retried here, since a plugin command may have partially executed side effects before it
+/// crashed.
+///
+/// Actually spawning the plugin process, and replaying the `Hello` handshake against the new one,
+/// isn't something this module does - that's supplied by the caller's `respawn` closure.
+///
+/// Nothing in this checkout constructs one yet: the process-spawning and reader-thread lifecycle
+/// that would own detecting [`Self::is_abnormal_termination`] and driving a respawn loop belongs
+/// to `PluginDeclaration::spawn_and_register` (in `crate::plugin::declaration`), and that function
+/// doesn't keep a handle to the `PluginInterfaceManager` it reads with - `make_plugin_interface`
+/// hands back only the client-side [`PluginInterface`], with the manager and its reader thread
+/// kept entirely internal to it. There's nowhere in this tree that both owns a
+/// `PluginInterfaceManager` long enough to notice it finished abnormally and has access to the
+/// `respawn` closure this type needs. Left as documented, acknowledged infrastructure rather than
+/// a silent `#[allow(dead_code)]`.
+#[allow(dead_code)]
+pub(crate) struct PluginSupervisor {
+    identity: Arc<PluginIdentity>,
+    max_restarts: u32,
+    restarts_used: Mutex<u32>,
+    /// The plugin's most recent stderr output, if the caller has been forwarding it via
+    /// [`Self::record_stderr`]. Attached to the error reported once every restart attempt is
+    /// exhausted, so a panicking plugin yields a diagnostic instead of an opaque decode failure.
+    last_stderr: Mutex<Option<String>>,
+}
+
+#[allow(dead_code)]
+impl PluginSupervisor {
+    pub(crate) fn new(identity: Arc<PluginIdentity>, max_restarts: u32) -> PluginSupervisor {
+        PluginSupervisor {
+            identity,
+            max_restarts,
+            restarts_used: Mutex::new(0),
+            last_stderr: Mutex::new(None),
+        }
+    }
+
+    /// Record the plugin's latest stderr output, so it can be attached to the next crash report.
+    /// Whoever owns the child process's stderr pipe should call this as output arrives; only the
+    /// most recently recorded chunk is kept.
+    pub(crate) fn record_stderr(&self, output: String) {
+        if let Ok(mut last_stderr) = self.last_stderr.lock() {
+            *last_stderr = Some(output);
+        }
+    }
+
+    /// True if `manager` stopped without every [`PluginInterface`] having already been dropped -
+    /// and is therefore a candidate for recovery rather than a clean, requested shutdown.
+    pub(crate) fn is_abnormal_termination(manager: &PluginInterfaceManager) -> bool {
+        !manager.is_finished()
+    }
+
+    /// Wrap a crash's `ShellError` with whatever stderr output was captured via
+    /// [`Self::record_stderr`].
+    fn annotate_with_stderr(&self, error: ShellError) -> ShellError {
+        match self.last_stderr.lock().ok().and_then(|guard| guard.clone()) {
+            Some(stderr) if !stderr.trim().is_empty() => ShellError::GenericError {
+                error: format!("Plugin {} crashed", self.identity.name()),
+                msg: error.to_string(),
+                span: None,
+                help: Some(format!("the plugin's last stderr output was:\n{stderr}")),
+                inner: vec![error],
+            },
+            _ => error,
+        }
+    }
+
+    /// Replay an idempotent plugin call (`Signature` or `CustomValueOp::ToBaseValue`) against a
+    /// freshly respawned plugin, up to `max_restarts` times, reporting the most recent failure
+    /// (annotated with captured stderr) if every attempt is exhausted. `build_call` constructs the
+    /// call fresh for each attempt, since [`PluginCall`] carries owned data that can't be reused.
+    /// `respawn` should construct a brand new [`PluginInterfaceManager`], replay the `Hello`
+    /// handshake, and return the resulting [`PluginInterface`]; actually spawning the process is
+    /// left to the caller, since this module doesn't do that.
+    pub(crate) fn retry_idempotent_call(
+        &self,
+        build_call: impl Fn() -> PluginCall<PipelineData>,
+        respawn: impl Fn() -> Result<PluginInterface, ShellError>,
+    ) -> Result<PluginCallResponse<PipelineData>, ShellError> {
+        let mut last_err = None;
+        loop {
+            {
+                let mut restarts_used =
+                    self.restarts_used
+                        .lock()
+                        .map_err(|_| ShellError::NushellFailed {
+                            msg: "PluginSupervisor restart counter mutex poisoned".into(),
+                        })?;
+                if *restarts_used >= self.max_restarts {
+                    break;
+                }
+                *restarts_used += 1;
+            }
+            match respawn().and_then(|interface| interface.plugin_call(build_call(), &None)) {
+                Ok(response) => return Ok(response),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(self.annotate_with_stderr(last_err.unwrap_or_else(|| {
+            ShellError::PluginFailedToLoad {
+                msg: format!(
+                    "Plugin {} crashed and could not be restarted after {} attempt(s)",
+                    self.identity.name(),
+                    self.max_restarts
+                ),
+            }
+        })))
     }
 }
@@ -1,5 +1,13 @@
-use std::{sync::{mpsc, Mutex, Condvar, Arc, MutexGuard, Weak}, marker::PhantomData, collections::BTreeMap};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    marker::PhantomData,
+    pin::Pin,
+    sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, Weak},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
 
+use futures::Stream;
 use nu_protocol::{Value, ShellError, Span};
 
 use crate::protocol::{StreamMessage, StreamId, StreamData};
@@ -7,6 +15,15 @@ use crate::protocol::{StreamMessage, StreamId, StreamData};
 #[cfg(test)]
 mod tests;
 
+/// Estimate how many bytes a [`StreamData`] costs against the byte-based flow control window,
+/// by measuring its serialized size. This is what lets a single giant value weigh proportionally
+/// more than a tiny one, rather than every message counting the same regardless of size.
+fn stream_data_size(data: &StreamData) -> u32 {
+    serde_json::to_vec(data)
+        .map(|bytes| bytes.len().try_into().unwrap_or(u32::MAX))
+        .unwrap_or(u32::MAX)
+}
+
 /// Receives messages from a stream read from input by a [`StreamManager`].
 ///
 /// The receiver reads for messages of type `Result<Option<StreamData>, ShellError>` from the
@@ -26,6 +43,9 @@ pub(crate) struct StreamReader<T, W> where W: WriteStreamMessage {
     id: StreamId,
     receiver: Option<mpsc::Receiver<Result<Option<StreamData>, ShellError>>>,
     writer: W,
+    /// Set by [`reset()`](Self::reset), so `Drop` doesn't also send a plain `Drop` message after
+    /// the more specific `Reset` has already gone out.
+    reset_sent: bool,
     /// Iterator requires the item type to be fixed, so we have to keep it as part of the type,
     /// even though we're actually receiving dynamic data.
     marker: PhantomData<fn() -> T>,
@@ -46,6 +66,7 @@ where
             id,
             receiver: Some(receiver),
             writer,
+            reset_sent: false,
             marker: PhantomData,
         }
     }
@@ -67,8 +88,11 @@ where
             })??;
 
             if let Some(data) = msg {
-                // Acknowledge the message
-                self.writer.write_stream_message(StreamMessage::Ack(self.id))?;
+                // Return the consumed bytes as credit to the writer, so it can send more. This
+                // is what keeps flow control accounted in bytes rather than messages: a big
+                // `StreamData` returns a correspondingly big credit, not just "one message worth".
+                let size = stream_data_size(&data);
+                self.writer.write_stream_message(StreamMessage::WindowUpdate(self.id, size))?;
                 // Try to convert it into the correct type
                 Ok(Some(data.try_into()?))
             } else {
@@ -81,6 +105,17 @@ where
             Ok(None)
         }
     }
+
+    /// Abort the stream because reading it, or something done with what was read, failed with
+    /// `err`. Sends [`StreamMessage::Reset`] rather than a plain [`StreamMessage::Drop`], so the
+    /// writer can tell this apart from a consumer that simply lost interest, and propagate `err`
+    /// to whatever produced the stream instead of failing silently.
+    pub(crate) fn reset(&mut self, err: ShellError) -> Result<(), ShellError> {
+        self.receiver = None;
+        self.reset_sent = true;
+        self.writer
+            .write_stream_message(StreamMessage::Reset(self.id, err))
+    }
 }
 
 impl<T, W> Iterator for StreamReader<T, W>
@@ -101,8 +136,12 @@ where
     W: WriteStreamMessage,
 {
     fn drop(&mut self) {
-        if let Err(err) = self.writer.write_stream_message(StreamMessage::Drop(self.id)) {
-            log::warn!("Failed to send message to drop stream: {err}");
+        // If `reset()` already sent a `Reset`, don't also send a `Drop`; the writer is already
+        // on its way out.
+        if !self.reset_sent {
+            if let Err(err) = self.writer.write_stream_message(StreamMessage::Drop(self.id)) {
+                log::warn!("Failed to send message to drop stream: {err}");
+            }
         }
     }
 }
@@ -126,6 +165,153 @@ impl<T> FromShellError for Result<T, ShellError> {
     }
 }
 
+/// State shared between an [`AsyncStreamReader`] and the [`StreamManager`] that feeds it: a
+/// buffer of messages not yet polled, plus whichever task's waker should be woken when the
+/// buffer goes from empty to non-empty. This is what lets `StreamManager::handle_message` push
+/// data in from a different thread without the reader having to block on it.
+#[derive(Debug, Default)]
+struct AsyncStreamReaderShared {
+    state: Mutex<AsyncStreamReaderState>,
+}
+
+#[derive(Debug, Default)]
+struct AsyncStreamReaderState {
+    buffer: VecDeque<Result<Option<StreamData>, ShellError>>,
+    waker: Option<Waker>,
+}
+
+impl AsyncStreamReaderShared {
+    /// Push a message onto the buffer, waking the polling task if one is registered.
+    ///
+    /// If the mutex is poisoned there's nothing sensible to do but drop the message; the reader
+    /// will just see no further progress, the same as if the channel had been disconnected.
+    fn push(&self, msg: Result<Option<StreamData>, ShellError>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.buffer.push_back(msg);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The async counterpart to [`StreamReader`]: a [`Stream`] that never blocks the current thread,
+/// registering the polling task's waker instead of parking when no data is available yet. This
+/// is what lets a plugin running on an async runtime drive many input streams from one task
+/// rather than dedicating an OS thread to each.
+///
+/// Preserves the same ack-on-receive and drop-on-close behavior as `StreamReader`: each item
+/// delivered by [`poll_next()`](Stream::poll_next) sends a [`StreamMessage::WindowUpdate`], and
+/// dropping the reader sends [`StreamMessage::Drop`].
+#[derive(Debug)]
+pub(crate) struct AsyncStreamReader<T, W>
+where
+    W: WriteStreamMessage,
+{
+    id: StreamId,
+    shared: Arc<AsyncStreamReaderShared>,
+    writer: W,
+    closed: bool,
+    /// See the note on the equivalent field of [`StreamReader`].
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, W> AsyncStreamReader<T, W>
+where
+    T: TryFrom<StreamData, Error = ShellError>,
+    W: WriteStreamMessage,
+{
+    /// Create a new AsyncStreamReader from parts
+    pub(crate) fn new(
+        id: StreamId,
+        shared: Arc<AsyncStreamReaderShared>,
+        writer: W,
+    ) -> AsyncStreamReader<T, W> {
+        AsyncStreamReader {
+            id,
+            shared,
+            writer,
+            closed: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, W> Stream for AsyncStreamReader<T, W>
+where
+    T: TryFrom<StreamData, Error = ShellError> + FromShellError,
+    W: WriteStreamMessage + Unpin,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+
+        if this.closed {
+            return Poll::Ready(None);
+        }
+
+        let mut state = match this.shared.state.lock() {
+            Ok(state) => state,
+            Err(_) => {
+                this.closed = true;
+                return Poll::Ready(Some(T::from_shell_error(ShellError::NushellFailed {
+                    msg: "AsyncStreamReader state mutex poisoned due to a panic".into(),
+                })));
+            }
+        };
+
+        let Some(msg) = state.buffer.pop_front() else {
+            // Nothing buffered yet: register our waker and wait to be woken by
+            // `StreamManager::handle_message`.
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        };
+        drop(state);
+
+        match msg {
+            Ok(Some(data)) => {
+                // Same flow-control accounting as `StreamReader::recv`: return the consumed
+                // bytes as credit to the writer.
+                let size = stream_data_size(&data);
+                if let Err(err) = this
+                    .writer
+                    .write_stream_message(StreamMessage::WindowUpdate(this.id, size))
+                {
+                    this.closed = true;
+                    return Poll::Ready(Some(T::from_shell_error(err)));
+                }
+                match data.try_into() {
+                    Ok(value) => Poll::Ready(Some(value)),
+                    Err(err) => {
+                        this.closed = true;
+                        Poll::Ready(Some(T::from_shell_error(err)))
+                    }
+                }
+            }
+            Ok(None) => {
+                this.closed = true;
+                Poll::Ready(None)
+            }
+            Err(err) => {
+                this.closed = true;
+                Poll::Ready(Some(T::from_shell_error(err)))
+            }
+        }
+    }
+}
+
+impl<T, W> Drop for AsyncStreamReader<T, W>
+where
+    W: WriteStreamMessage,
+{
+    fn drop(&mut self) {
+        if let Err(err) = self.writer.write_stream_message(StreamMessage::Drop(self.id)) {
+            log::warn!("Failed to send message to drop stream: {err}");
+        }
+    }
+}
+
 /// Writes messages to a stream, with flow control.
 ///
 /// The `signal` contained 
@@ -153,14 +339,29 @@ impl<W> StreamWriter<W> where W: WriteStreamMessage {
         self.signal.is_dropped()
     }
 
+    /// If the stream was dropped *because the reader hit an error* (a [`StreamMessage::Reset`]
+    /// rather than a plain [`StreamMessage::Drop`]), returns that error. `None` for a plain drop,
+    /// or if the stream isn't dropped at all.
+    pub(crate) fn dropped_reason(&self) -> Result<Option<ShellError>, ShellError> {
+        self.signal.dropped_reason()
+    }
+
     /// Write a single piece of data to the stream.
     ///
-    /// Error if something failed with the write, or if [`end()`] was already called previously.
+    /// Error if something failed with the write, if the stream was reset with an error from the
+    /// other end, or if [`end()`] was already called previously.
     pub(crate) fn write(&mut self, data: impl Into<StreamData>) -> Result<(), ShellError> {
         if !self.ended {
-            self.writer.write_stream_message(StreamMessage::Data(self.id, data.into()))?;
-            // This implements flow control, so we don't write too many messages:
-            self.signal.notify_sent()
+            if let Some(err) = self.dropped_reason()? {
+                return Err(err);
+            }
+            let data = data.into();
+            // This implements flow control, accounted in bytes rather than message count, so a
+            // few huge values can't blow past the same budget that's meant to bound a stream of
+            // many small ones.
+            let size = stream_data_size(&data);
+            self.writer.write_stream_message(StreamMessage::Data(self.id, data))?;
+            self.signal.notify_sent(size, &mut self.writer)
         } else {
             Err(ShellError::GenericError {
                 error: "Wrote to a stream after it ended".into(),
@@ -178,8 +379,10 @@ impl<W> StreamWriter<W> where W: WriteStreamMessage {
     /// If the stream is dropped from the other end, the iterator will not be fully consumed, and
     /// writing will terminate.
     ///
-    /// Returns `Ok(true)` if the iterator was fully consumed, or `Ok(false)` if a drop interrupted
-    /// the stream from the other side.
+    /// Returns `Ok(true)` if the iterator was fully consumed, or `Ok(false)` if a plain drop
+    /// interrupted the stream from the other side. If the other side reset the stream with an
+    /// error instead, that error is returned rather than `Ok(false)`, so a producer doing
+    /// expensive work can abort with a real diagnostic instead of failing silently.
     pub(crate) fn write_all<T>(
         &mut self,
         data: impl IntoIterator<Item=T>,
@@ -188,6 +391,9 @@ impl<W> StreamWriter<W> where W: WriteStreamMessage {
         T: Into<StreamData>,
     {
         // Check before starting
+        if let Some(err) = self.dropped_reason()? {
+            return Err(err);
+        }
         if self.is_dropped()? {
             return Ok(false);
         }
@@ -195,6 +401,9 @@ impl<W> StreamWriter<W> where W: WriteStreamMessage {
         for item in data {
             // Check again after each item is consumed from the iterator, just in case the iterator
             // takes a while to produce a value
+            if let Some(err) = self.dropped_reason()? {
+                return Err(err);
+            }
             if self.is_dropped()? {
                 return Ok(false);
             }
@@ -209,7 +418,9 @@ impl<W> StreamWriter<W> where W: WriteStreamMessage {
         if !self.ended {
             // Set the flag first so we don't double-report in the Drop
             self.ended = true;
-            self.writer.write_stream_message(StreamMessage::End(self.id))
+            self.writer.write_stream_message(StreamMessage::End(self.id))?;
+            // Lets `StreamManager::shutdown_graceful` know this writer has nothing more coming.
+            self.signal.set_ended()
         } else {
             Ok(())
         }
@@ -225,6 +436,86 @@ impl<W> Drop for StreamWriter<W> where W: WriteStreamMessage {
     }
 }
 
+/// Wraps a [`StreamWriter`] to coalesce many small items into one [`StreamData::Batch`], so a
+/// list stream of many tiny values doesn't pay a `Data` message and ack round-trip per item.
+///
+/// Items are buffered until either `cap` of them have accumulated, or `flush_deadline` has
+/// elapsed since the first buffered item, at which point they're flushed automatically on the
+/// next [`push()`](Self::push). Call [`push_now()`](Self::push_now) to force a partial batch out
+/// sooner, e.g. once it's known no more items are coming for a while. A batch is never emitted
+/// empty. A batch is charged against the window by the serialized size of the whole batch; the
+/// reader credits each item back as it's read, and [`StreamManager::handle_message`] credits the
+/// batch's framing overhead back on receipt, so the window nets out to the sum of its parts.
+#[derive(Debug)]
+pub(crate) struct BatchingStreamWriter<W: WriteStreamMessage> {
+    inner: StreamWriter<W>,
+    cap: usize,
+    flush_deadline: Duration,
+    buffer: Vec<StreamData>,
+    buffer_started_at: Option<Instant>,
+}
+
+impl<W> BatchingStreamWriter<W>
+where
+    W: WriteStreamMessage,
+{
+    /// Wrap `inner`, buffering up to `cap` items or `flush_deadline` before emitting a batch.
+    pub(crate) fn new(inner: StreamWriter<W>, cap: usize, flush_deadline: Duration) -> Self {
+        assert!(cap > 0);
+        BatchingStreamWriter {
+            inner,
+            cap,
+            flush_deadline,
+            buffer: Vec::new(),
+            buffer_started_at: None,
+        }
+    }
+
+    /// Check if the stream was dropped from the other end. See [`StreamWriter::is_dropped`].
+    pub(crate) fn is_dropped(&self) -> Result<bool, ShellError> {
+        self.inner.is_dropped()
+    }
+
+    fn deadline_elapsed(&self) -> bool {
+        self.buffer_started_at
+            .is_some_and(|start| start.elapsed() >= self.flush_deadline)
+    }
+
+    /// Buffer a single piece of data, flushing the batch out first if buffering this item would
+    /// exceed `cap`, or if `flush_deadline` has elapsed since the oldest buffered item.
+    pub(crate) fn push(&mut self, data: impl Into<StreamData>) -> Result<(), ShellError> {
+        if self.buffer.len() >= self.cap || self.deadline_elapsed() {
+            self.push_now()?;
+        }
+        self.buffer.push(data.into());
+        self.buffer_started_at.get_or_insert_with(Instant::now);
+        Ok(())
+    }
+
+    /// Force out whatever is currently buffered as a single batch, even if under `cap`. Does
+    /// nothing if the buffer is empty, so this never emits an empty batch.
+    pub(crate) fn push_now(&mut self) -> Result<(), ShellError> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let items = std::mem::take(&mut self.buffer);
+        self.buffer_started_at = None;
+        self.inner.write(StreamData::Batch(items))
+    }
+
+    /// Alias for [`push_now()`](Self::push_now), for callers that want to flush without framing
+    /// it as "forcing a partial batch".
+    pub(crate) fn flush(&mut self) -> Result<(), ShellError> {
+        self.push_now()
+    }
+
+    /// Flush any pending buffer, then end the stream. See [`StreamWriter::end`].
+    pub(crate) fn end(&mut self) -> Result<(), ShellError> {
+        self.push_now()?;
+        self.inner.end()
+    }
+}
+
 /// Stores stream state for a writer, and can be blocked on to wait for messages to be acknowledged.
 /// A key part of managing stream lifecycle and flow control.
 #[derive(Debug)]
@@ -237,26 +528,138 @@ pub(crate) struct StreamWriterSignal {
 pub(crate) struct StreamWriterSignalState {
     /// Stream has been dropped and consumer is no longer interested in any messages.
     dropped: bool,
-    /// Number of messages that have been sent without acknowledgement.
-    unacknowledged: i32,
-    /// Max number of messages to send before waiting for acknowledgement.
-    high_pressure_mark: i32,
+    /// Set along with `dropped` when the reader sent [`StreamMessage::Reset`] rather than a
+    /// plain [`StreamMessage::Drop`]: the consumer didn't just lose interest, it hit an error.
+    /// Callers should surface this instead of treating the drop as a clean early stop.
+    reset_reason: Option<ShellError>,
+    /// The peer stopped responding while we were blocked on it (see [`KeepaliveConfig`]) and is
+    /// assumed hung or crashed. Unlike `dropped`, waiting on this is an error, not a clean stop.
+    dead: bool,
+    /// Set once [`StreamWriter::end`] has sent its `End` message. Combined with `window` being
+    /// back at `max_window` (nothing still in flight), this is what
+    /// [`StreamManager::shutdown_graceful`] waits for before considering the stream drained.
+    ended: bool,
+    /// Bytes of credit remaining to send before waiting for the consumer to release more, by
+    /// acknowledging bytes it has consumed. Signed, because a single item bigger than the whole
+    /// window is still let through (see `notify_sent`), which can drive this negative.
+    window: i64,
+    /// The window's current max size, i.e. how much credit is available when nothing is in
+    /// flight. `release_window` never lets `window` grow past this. Auto-tuning only ever raises
+    /// this value; it starts at, and never drops below, [`StreamWindowConfig::starting`].
+    max_window: u32,
+    /// When the window most recently became saturated (credit reached zero or below). Used both
+    /// to measure an ack round-trip time sample for auto-tuning, and to gate keepalive pings.
+    /// Cleared once the window is usable again.
+    blocked_since: Option<Instant>,
+    /// Auto-tuning state, present only when [`StreamWindowConfig::auto_tune`] was set.
+    tuning: Option<WindowTuning>,
+    /// Keepalive liveness-check config, present only when it was enabled on the owning
+    /// [`StreamManager`].
+    keepalive: Option<KeepaliveConfig>,
+    /// A `Ping` nonce we're waiting on a matching `Pong` for, and when it was sent. Cleared once
+    /// the window unblocks, win or lose.
+    pending_ping: Option<(u64, Instant)>,
+}
+
+/// h2-style auto-tuning of [`StreamWriterSignalState::max_window`], based on how much of the
+/// time a writer actually spends blocked waiting on credit. Measured in fixed-length intervals:
+/// at the end of each interval, if the writer was blocked more than
+/// [`BLOCKED_FRACTION_THRESHOLD`] of the interval, that counts as one "saturated" interval: after
+/// [`SATURATED_INTERVALS_TO_GROW`] of those in a row, the window doubles, up to `ceiling`.
+#[derive(Debug)]
+struct WindowTuning {
+    /// Never grow `max_window` past this.
+    ceiling: u32,
+    /// Exponentially-weighted moving average of ack round-trip time, kept for visibility into
+    /// why the window grew (logged alongside each doubling) rather than to gate it directly.
+    avg_rtt: Option<Duration>,
+    /// Start of the current measurement interval.
+    interval_start: Instant,
+    /// Time spent blocked in `notify_sent` so far during the current interval.
+    interval_blocked: Duration,
+    /// How many consecutive intervals have been more than `BLOCKED_FRACTION_THRESHOLD` blocked.
+    consecutive_saturated_intervals: u32,
+}
+
+/// How often auto-tuning re-evaluates whether the window is the bottleneck.
+const AUTO_TUNE_INTERVAL: Duration = Duration::from_secs(1);
+/// Fraction of an interval spent blocked in `notify_sent` above which the window counts as the
+/// bottleneck for that interval.
+const BLOCKED_FRACTION_THRESHOLD: f64 = 0.5;
+/// How many consecutive saturated intervals it takes to double the window. Requiring several in
+/// a row (rather than growing on the first) avoids reacting to a single brief burst.
+const SATURATED_INTERVALS_TO_GROW: u32 = 3;
+/// Smoothing factor for the RTT EWMA: higher weights recent samples more heavily.
+const RTT_EWMA_ALPHA: f64 = 0.25;
+/// How often a blocked `notify_sent` re-checks whether it's time to send a keepalive ping, or
+/// whether `dead` was set by another thread, rather than sleeping until `release_window` wakes it.
+const NOTIFY_SENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Next nonce to hand out for a keepalive `Ping`. Process-wide and monotonically increasing is
+/// enough to make a `Pong` unambiguous; it doesn't need to be tied to a particular stream.
+static NEXT_PING_NONCE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+fn next_ping_nonce() -> u64 {
+    NEXT_PING_NONCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Starting size, ceiling, and auto-tuning switch for a [`StreamWriterSignal`]'s flow control
+/// window, passed to [`StreamManagerHandle::write_stream`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StreamWindowConfig {
+    /// The window size to start at, and the floor auto-tuning never shrinks back below.
+    pub starting: u32,
+    /// The most auto-tuning is ever allowed to grow the window to.
+    pub ceiling: u32,
+    /// Whether to grow the window automatically when it's observed to be the bottleneck.
+    pub auto_tune: bool,
+}
+
+/// HTTP/2-style ping/pong liveness detection for a writer blocked on flow control credit.
+///
+/// A writer that's been blocked longer than `idle_interval` sends a [`StreamMessage::Ping`] and
+/// expects the peer to echo it back as a [`StreamMessage::Pong`] within `pong_timeout`. If it
+/// doesn't, the peer is assumed hung or crashed, and the blocked writer is unblocked with an
+/// error instead of waiting forever. Disabled (`None`) by default; configured at
+/// [`StreamManager::new`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KeepaliveConfig {
+    /// How long a writer has to be blocked before it pings the peer to check it's still there.
+    pub idle_interval: Duration,
+    /// How long to wait for a `Pong` in response to a `Ping` before giving up on the peer.
+    pub pong_timeout: Duration,
 }
 
 impl StreamWriterSignal {
-    /// Create a new signal.
+    /// Create a new signal, with a byte-based flow control window configured by `config`, and
+    /// optional keepalive liveness detection configured by `keepalive`.
     ///
-    /// If `notify_sent()` is called more than `high_pressure_mark` times, it will wait until
-    /// `notify_acknowledge()` is called by another thread enough times to bring the number of
-    /// unacknowledged sent messages below that threshold.
-    pub fn new(high_pressure_mark: i32) -> StreamWriterSignal {
-        assert!(high_pressure_mark > 0);
+    /// If `notify_sent()` is called with enough cumulative size to exhaust the window, it will
+    /// wait until `release_window()` is called by another thread with enough released credit to
+    /// bring the window back above zero. If `config.auto_tune` is set, a window that's
+    /// frequently the bottleneck grows on its own, up to `config.ceiling`.
+    pub fn new(config: StreamWindowConfig, keepalive: Option<KeepaliveConfig>) -> StreamWriterSignal {
+        assert!(config.starting > 0);
+        assert!(config.ceiling >= config.starting);
 
         StreamWriterSignal {
             mutex: Mutex::new(StreamWriterSignalState {
                 dropped: false,
-                unacknowledged: 0,
-                high_pressure_mark
+                reset_reason: None,
+                dead: false,
+                ended: false,
+                window: i64::from(config.starting),
+                max_window: config.starting,
+                blocked_since: None,
+                tuning: config.auto_tune.then(|| WindowTuning {
+                    ceiling: config.ceiling,
+                    avg_rtt: None,
+                    interval_start: Instant::now(),
+                    interval_blocked: Duration::ZERO,
+                    consecutive_saturated_intervals: 0,
+                }),
+                keepalive,
+                pending_ping: None,
             }),
             change_cond: Condvar::new()
         }
@@ -274,6 +677,12 @@ impl StreamWriterSignal {
         Ok(self.lock()?.dropped)
     }
 
+    /// If the stream was dropped because the reader hit an error (see [`set_reset`](Self::set_reset)),
+    /// returns that error. `None` for a plain drop, or if the stream isn't dropped at all.
+    pub fn dropped_reason(&self) -> Result<Option<ShellError>, ShellError> {
+        Ok(self.lock()?.reset_reason.clone())
+    }
+
     /// Notify the writers that the stream has been dropped, so they can stop writing.
     pub fn set_dropped(&self) -> Result<(), ShellError> {
         let mut state = self.lock()?;
@@ -283,36 +692,228 @@ impl StreamWriterSignal {
         Ok(())
     }
 
-    /// Track that a message has been sent, and wait for the manager to receive acknowledgements
-    /// if too many messages have been sent.
-    pub fn notify_sent(&self) -> Result<(), ShellError> {
+    /// Like [`set_dropped`](Self::set_dropped), but also records `err` as the reason, so the
+    /// writer can propagate a real diagnostic instead of treating this as a clean early stop.
+    pub fn set_reset(&self, err: ShellError) -> Result<(), ShellError> {
         let mut state = self.lock()?;
-        state.unacknowledged = state.unacknowledged.checked_add(1)
-            .ok_or_else(|| ShellError::NushellFailed {
-                msg: "Overflow in counter: too many unacknowledged messages".into(),
-            })?;
+        state.dropped = true;
+        state.reset_reason = Some(err);
+        // Unblock the writers so they can terminate
+        self.change_cond.notify_all();
+        Ok(())
+    }
+
+    /// Record that [`StreamWriter::end`] has sent its `End` message, and wake anyone waiting in
+    /// [`wait_drained`](Self::wait_drained) in case nothing is in flight anymore either.
+    pub fn set_ended(&self) -> Result<(), ShellError> {
+        let mut state = self.lock()?;
+        state.ended = true;
+        self.change_cond.notify_all();
+        Ok(())
+    }
 
-        // Wait if too many messages have been sent
-        while !state.dropped && state.unacknowledged >= state.high_pressure_mark {
-            state = self.change_cond.wait(state).map_err(|_| ShellError::NushellFailed {
-                msg: "StreamWriterSignal mutex poisoned due to panic".into()
+    /// Block until the stream is drained (`End` sent and all in-flight credit released back to
+    /// `max_window`) or dropped, or `timeout` elapses, whichever comes first. Returns `true` if
+    /// drained or dropped, `false` on timeout. Used by
+    /// [`StreamManager::shutdown_graceful`] to let buffered data flush before tearing a writer
+    /// down, rather than truncating it mid-message.
+    pub fn wait_drained(&self, timeout: Duration) -> Result<bool, ShellError> {
+        let state = self.lock()?;
+        let (state, timeout_result) = self
+            .change_cond
+            .wait_timeout_while(state, timeout, |state| {
+                !state.dropped && !(state.ended && state.window >= i64::from(state.max_window))
+            })
+            .map_err(|_| ShellError::NushellFailed {
+                msg: "StreamWriterSignal mutex poisoned due to panic".into(),
             })?;
+        drop(state);
+        Ok(!timeout_result.timed_out())
+    }
+
+    /// Track that `size` bytes have been sent, waiting first if the window has already been
+    /// exhausted by earlier sends.
+    ///
+    /// Checking the window *before* subtracting (rather than after) means a single item bigger
+    /// than the whole window still gets sent through once the window is positive again: it just
+    /// drives the window deeply negative, so the next send waits for enough credit back to
+    /// recover. This is what keeps a huge one-off value from deadlocking against its own size.
+    ///
+    /// If keepalive is enabled, a long-blocked wait periodically pings the peer through `writer`
+    /// and gives up with an error if it never answers, rather than waiting forever on a hung or
+    /// crashed consumer.
+    pub fn notify_sent<W: WriteStreamMessage>(
+        &self,
+        size: u32,
+        writer: &mut W,
+    ) -> Result<(), ShellError> {
+        let mut state = self.lock()?;
+
+        if state.window <= 0 {
+            let block_start = Instant::now();
+            state.blocked_since.get_or_insert(block_start);
+
+            while !state.dropped && !state.dead && state.window <= 0 {
+                let ping = state
+                    .keepalive
+                    .and_then(|keepalive| Self::check_keepalive(&mut state, keepalive));
+
+                if let Some(nonce) = ping {
+                    // Don't hold the mutex across the actual write.
+                    drop(state);
+                    let sent = writer.write_stream_message(StreamMessage::Ping(nonce));
+                    state = self.lock()?;
+                    sent?;
+                    continue;
+                }
+
+                let (guard, _) = self
+                    .change_cond
+                    .wait_timeout(state, NOTIFY_SENT_POLL_INTERVAL)
+                    .map_err(|_| ShellError::NushellFailed {
+                        msg: "StreamWriterSignal mutex poisoned due to panic".into(),
+                    })?;
+                state = guard;
+            }
+
+            if state.dead {
+                return Err(ShellError::GenericError {
+                    error: "Stream writer timed out waiting for the peer".into(),
+                    msg: "no response to a keepalive ping within the configured timeout".into(),
+                    span: None,
+                    help: Some("the plugin or engine on the other end may have hung or crashed".into()),
+                    inner: vec![]
+                });
+            }
+
+            // The reader reset the stream with an error rather than just dropping it: surface
+            // that instead of letting this send through silently.
+            if let Some(err) = state.reset_reason.clone() {
+                return Err(err);
+            }
+
+            let blocked_for = block_start.elapsed();
+            if let Some(tuning) = state.tuning.as_mut() {
+                tuning.interval_blocked = tuning.interval_blocked.saturating_add(blocked_for);
+            }
+        }
+
+        state.window = state.window.saturating_sub(i64::from(size));
+
+        if Self::maybe_tune(&mut state) {
+            self.change_cond.notify_all();
         }
         Ok(())
     }
 
-    /// Notify the writers that a message has been acknowledged, so they can continue to write
-    /// if they were waiting.
-    pub fn notify_acknowledged(&self) -> Result<(), ShellError> {
+    /// If keepalive is due, either send a fresh `Ping` (returned so the caller can send it
+    /// without holding the lock) or, if a previously-sent `Ping` has gone unanswered for longer
+    /// than `pong_timeout`, mark the signal `dead`.
+    fn check_keepalive(state: &mut StreamWriterSignalState, keepalive: KeepaliveConfig) -> Option<u64> {
+        if let Some((_, sent_at)) = state.pending_ping {
+            if sent_at.elapsed() >= keepalive.pong_timeout {
+                state.dead = true;
+            }
+            return None;
+        }
+
+        let blocked_since = state.blocked_since?;
+        if blocked_since.elapsed() < keepalive.idle_interval {
+            return None;
+        }
+
+        let nonce = next_ping_nonce();
+        state.pending_ping = Some((nonce, Instant::now()));
+        Some(nonce)
+    }
+
+    /// Resolve a `Pong` received for `nonce`, if this signal was the one waiting on it. Does
+    /// nothing if this signal has no pending ping, or is waiting on a different nonce (e.g. a
+    /// stale `Pong` for a ping that already timed out).
+    pub fn resolve_pong(&self, nonce: u64) -> Result<(), ShellError> {
         let mut state = self.lock()?;
-        state.unacknowledged = state.unacknowledged.checked_sub(1)
-            .ok_or_else(|| ShellError::NushellFailed {
-                msg: "Underflow in counter: too many message acknowledgements".into()
-            })?;
+        if state.pending_ping.is_some_and(|(pending, _)| pending == nonce) {
+            state.pending_ping = None;
+        }
+        Ok(())
+    }
+
+    /// Release `size` bytes of credit back to the window (the consumer has finished with that
+    /// many bytes), waking a blocked writer if the window is now usable again. Saturates at
+    /// `max_window` rather than overflowing, and never grows the window past its configured max.
+    pub fn release_window(&self, size: u32) -> Result<(), ShellError> {
+        let mut state = self.lock()?;
+        let was_saturated = state.window <= 0;
+
+        state.window = state.window
+            .saturating_add(i64::from(size))
+            .min(i64::from(state.max_window));
+
+        if was_saturated && state.window > 0 {
+            state.pending_ping = None;
+            if let Some(blocked_since) = state.blocked_since.take() {
+                if let Some(tuning) = state.tuning.as_mut() {
+                    let rtt = blocked_since.elapsed();
+                    tuning.avg_rtt = Some(match tuning.avg_rtt {
+                        Some(prev) => prev.mul_f64(1.0 - RTT_EWMA_ALPHA) + rtt.mul_f64(RTT_EWMA_ALPHA),
+                        None => rtt,
+                    });
+                }
+            }
+        }
+
+        let grew = Self::maybe_tune(&mut state);
         // Unblock the writer
         self.change_cond.notify_one();
+        if grew {
+            self.change_cond.notify_all();
+        }
         Ok(())
     }
+
+    /// Re-evaluate auto-tuning at the end of each [`AUTO_TUNE_INTERVAL`]: if the writer spent
+    /// more than [`BLOCKED_FRACTION_THRESHOLD`] of several consecutive intervals blocked on
+    /// window credit, double `max_window` (and give the extra credit to `window` immediately, so
+    /// a writer blocked right now wakes up with it) up to the configured ceiling. Returns whether
+    /// the window grew, so the caller knows to wake blocked writers.
+    fn maybe_tune(state: &mut StreamWriterSignalState) -> bool {
+        let Some(tuning) = state.tuning.as_mut() else {
+            return false;
+        };
+
+        let elapsed = tuning.interval_start.elapsed();
+        if elapsed < AUTO_TUNE_INTERVAL {
+            return false;
+        }
+
+        let blocked_fraction = tuning.interval_blocked.as_secs_f64() / elapsed.as_secs_f64();
+        if blocked_fraction > BLOCKED_FRACTION_THRESHOLD {
+            tuning.consecutive_saturated_intervals += 1;
+        } else {
+            tuning.consecutive_saturated_intervals = 0;
+        }
+
+        let mut grew = false;
+        if tuning.consecutive_saturated_intervals >= SATURATED_INTERVALS_TO_GROW {
+            let doubled = state.max_window.saturating_mul(2).min(tuning.ceiling);
+            if doubled > state.max_window {
+                log::trace!(
+                    "growing plugin stream window from {} to {doubled} bytes (avg ack rtt: {:?})",
+                    state.max_window,
+                    tuning.avg_rtt,
+                );
+                let delta = doubled - state.max_window;
+                state.max_window = doubled;
+                state.window = state.window.saturating_add(i64::from(delta));
+                grew = true;
+            }
+            tuning.consecutive_saturated_intervals = 0;
+        }
+
+        tuning.interval_start = Instant::now();
+        tuning.interval_blocked = Duration::ZERO;
+        grew
+    }
 }
 
 /// A sink for a [`StreamMessage`]
@@ -320,10 +921,38 @@ pub(crate) trait WriteStreamMessage {
     fn write_stream_message(&mut self, msg: StreamMessage) -> Result<(), ShellError>;
 }
 
+/// Where a received stream message ends up: either a blocking [`StreamReader`]'s channel, or an
+/// [`AsyncStreamReader`]'s shared, waker-aware buffer.
+#[derive(Debug)]
+enum ReadStreamSink {
+    Blocking(mpsc::Sender<Result<Option<StreamData>, ShellError>>),
+    Async(Arc<AsyncStreamReaderShared>),
+}
+
+impl ReadStreamSink {
+    /// Deliver a message to the reader. Ignoring the error, if any, is deliberate: it just means
+    /// the reader has dropped, but it will have sent a `Drop` message to the other side, and
+    /// we'll receive an `End` message at which point we can remove the channel.
+    fn send(&self, msg: Result<Option<StreamData>, ShellError>) {
+        match self {
+            ReadStreamSink::Blocking(tx) => {
+                let _ = tx.send(msg);
+            }
+            ReadStreamSink::Async(shared) => shared.push(msg),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct StreamManagerState {
-    reading_streams: BTreeMap<StreamId, mpsc::Sender<Result<Option<StreamData>, ShellError>>>,
+    reading_streams: BTreeMap<StreamId, ReadStreamSink>,
     writing_streams: BTreeMap<StreamId, Weak<StreamWriterSignal>>,
+    /// Keepalive config to hand to each [`StreamWriterSignal`] created by `write_stream`. `None`
+    /// (the default) disables keepalive entirely, preserving the old wait-forever behavior.
+    keepalive: Option<KeepaliveConfig>,
+    /// Set by [`StreamManager::shutdown_graceful`] so that further registration attempts fail
+    /// cleanly instead of inserting into a map that's already being torn down.
+    shutting_down: bool,
 }
 
 impl StreamManagerState {
@@ -346,9 +975,14 @@ impl StreamManager {
         StreamManagerState::lock(&self.state)
     }
 
-    pub(crate) fn new() -> StreamManager {
+    /// Create a new stream manager. `keepalive` configures ping/pong liveness detection for
+    /// blocked writers; pass `None` to disable it and preserve the old wait-forever behavior.
+    pub(crate) fn new(keepalive: Option<KeepaliveConfig>) -> StreamManager {
         StreamManager {
-            state: Default::default(),
+            state: Arc::new(Mutex::new(StreamManagerState {
+                keepalive,
+                ..Default::default()
+            })),
         }
     }
 
@@ -358,15 +992,42 @@ impl StreamManager {
         }
     }
 
-    pub(crate) fn handle_message(&self, message: StreamMessage) -> Result<(), ShellError> {
+    /// Handle a message received from the peer. `writer` is used to reply to a `Ping` with a
+    /// matching `Pong`, and to credit back a received batch's framing overhead; other message
+    /// kinds only touch local state.
+    pub(crate) fn handle_message<W: WriteStreamMessage>(
+        &self,
+        message: StreamMessage,
+        writer: &mut W,
+    ) -> Result<(), ShellError> {
         let mut state = self.lock()?;
         match message {
             StreamMessage::Data(id, data) => {
-                if let Some(sender) = state.reading_streams.get(&id) {
-                    // We should ignore the error on send. This just means the reader has dropped,
-                    // but it will have sent a Drop message to the other side, and we will receive
-                    // an End message at which point we can remove the channel.
-                    let _ = sender.send(Ok(Some(data)));
+                if let Some(sink) = state.reading_streams.get(&id) {
+                    // A `Batch` is expanded back into its individual items here, so that neither
+                    // `StreamReader::recv` nor `AsyncStreamReader::poll_next` ever has to know
+                    // batching happened on the other end.
+                    let batch_size =
+                        matches!(data, StreamData::Batch(_)).then(|| stream_data_size(&data));
+                    match data {
+                        StreamData::Batch(items) => {
+                            // The writer was charged for the whole serialized batch, but the
+                            // reader credits each item back on its own, so return the batch's
+                            // framing overhead here or that credit would never come back.
+                            let item_total = items
+                                .iter()
+                                .map(stream_data_size)
+                                .fold(0u32, u32::saturating_add);
+                            for item in items {
+                                sink.send(Ok(Some(item)));
+                            }
+                            let overhead = batch_size.unwrap_or(0).saturating_sub(item_total);
+                            if overhead > 0 {
+                                writer.write_stream_message(StreamMessage::WindowUpdate(id, overhead))?;
+                            }
+                        }
+                        other => sink.send(Ok(Some(other))),
+                    }
                     Ok(())
                 } else {
                     Err(ShellError::PluginFailedToDecode {
@@ -375,10 +1036,8 @@ impl StreamManager {
                 }
             }
             StreamMessage::End(id) => {
-                if let Some(sender) = state.reading_streams.remove(&id) {
-                    // We should ignore the error on the send, because the reader might have dropped
-                    // already
-                    let _ = sender.send(Ok(None));
+                if let Some(sink) = state.reading_streams.remove(&id) {
+                    sink.send(Ok(None));
                     Ok(())
                 } else {
                     Err(ShellError::PluginFailedToDecode {
@@ -397,11 +1056,23 @@ impl StreamManager {
                 // anymore, so we fall through to Ok
                 Ok(())
             }
-            StreamMessage::Ack(id) => {
+            StreamMessage::Reset(id, err) => {
+                if let Some(signal) = state.writing_streams.remove(&id) {
+                    if let Some(signal) = signal.upgrade() {
+                        // This will wake blocked writers so they can stop writing, with `err` as
+                        // the reason, so it's ok
+                        signal.set_reset(err)?;
+                    }
+                }
+                // It's possible that the stream has already finished writing and we don't have it
+                // anymore, so we fall through to Ok
+                Ok(())
+            }
+            StreamMessage::WindowUpdate(id, size) => {
                 if let Some(signal) = state.writing_streams.get(&id) {
                     if let Some(signal) = signal.upgrade() {
                         // This will wake up a blocked writer
-                        signal.notify_acknowledged()?;
+                        signal.release_window(size)?;
                     } else {
                         // We know it doesn't exist, so might as well remove it
                         state.writing_streams.remove(&id);
@@ -411,9 +1082,54 @@ impl StreamManager {
                 // anymore, so we fall through to Ok
                 Ok(())
             },
+            StreamMessage::Ping(nonce) => {
+                // The peer is checking we're still alive; echo the nonce straight back.
+                drop(state);
+                writer.write_stream_message(StreamMessage::Pong(nonce))
+            }
+            StreamMessage::Pong(nonce) => {
+                // We don't track which signal sent which ping, since keepalive is a per-writer
+                // liveness check rather than a per-stream one: just offer it to every writer
+                // currently blocked and let the one actually waiting on this nonce claim it.
+                for signal in state.writing_streams.values().filter_map(|weak| weak.upgrade()) {
+                    signal.resolve_pong(nonce)?;
+                }
+                Ok(())
+            }
         }
     }
 
+    /// Give every writer a chance to finish draining before the manager goes away, instead of
+    /// the abrupt `Drop` behavior of [`drop_all_writers`](Self::drop_all_writers). Like yamux
+    /// closing a session out from under a stream that still has pending frames in flight, just
+    /// dropping the manager can discard data the peer has already been sent credit for; this
+    /// waits (up to `timeout` in total, taking the writers one after another, each getting
+    /// whatever is left of it) for each
+    /// writer to call [`StreamWriter::end`] *and* for its outstanding window to be returned,
+    /// and only then lets it go. Writers that don't finish in time are dropped anyway so shutdown
+    /// can't hang forever. Reading streams are left alone; they'll see their `End` normally.
+    pub(crate) fn shutdown_graceful(&self, timeout: Duration) -> Result<(), ShellError> {
+        let signals = {
+            let mut state = self.lock()?;
+            state.shutting_down = true;
+            state
+                .writing_streams
+                .values()
+                .filter_map(|weak| weak.upgrade())
+                .collect::<Vec<_>>()
+        };
+
+        let deadline = Instant::now() + timeout;
+        for signal in signals {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !signal.wait_drained(remaining)? {
+                // Ran out of time waiting for this one; cut it loose rather than block forever.
+                signal.set_dropped()?;
+            }
+        }
+        Ok(())
+    }
+
     // If the `StreamManager` is dropped, we should let all of the stream writers know that they
     // won't be able to write anymore. We don't need to do anything about the readers though
     // because they'll know when the `Sender` is dropped automatically
@@ -457,6 +1173,22 @@ impl StreamManagerHandle {
         f(guard)
     }
 
+    /// Reject new stream registrations once [`StreamManager::shutdown_graceful`] has started
+    /// tearing things down, rather than letting them race the drain.
+    fn check_not_shutting_down(state: &StreamManagerState) -> Result<(), ShellError> {
+        if state.shutting_down {
+            Err(ShellError::GenericError {
+                error: "StreamManager is shutting down".into(),
+                msg: "tried to register a new stream while the manager was shutting down".into(),
+                span: None,
+                help: Some("this may be a bug in the nu-plugin crate".into()),
+                inner: vec![]
+            })
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn read_stream<T, W>(
         &self,
         id: StreamId,
@@ -468,9 +1200,10 @@ impl StreamManagerHandle {
     {
         let (tx, rx) = mpsc::channel();
         self.with_lock(|mut state| {
+            Self::check_not_shutting_down(&state)?;
             // Must be exclusive
             if !state.reading_streams.contains_key(&id) {
-                state.reading_streams.insert(id, tx);
+                state.reading_streams.insert(id, ReadStreamSink::Blocking(tx));
                 Ok(())
             } else {
                 Err(ShellError::GenericError {
@@ -485,23 +1218,59 @@ impl StreamManagerHandle {
         Ok(StreamReader::new(id, rx, writer))
     }
 
+    /// The async counterpart to [`read_stream()`](Self::read_stream): returns an
+    /// [`AsyncStreamReader`] that's polled rather than blocked on, so a plugin running on an
+    /// async runtime doesn't need to dedicate an OS thread to this stream. Synchronous plugins
+    /// are unaffected; they keep using `read_stream`.
+    pub(crate) fn read_stream_async<T, W>(
+        &self,
+        id: StreamId,
+        writer: W,
+    ) -> Result<AsyncStreamReader<T, W>, ShellError>
+    where
+        T: TryFrom<StreamData, Error = ShellError>,
+        W: WriteStreamMessage,
+    {
+        let shared = Arc::new(AsyncStreamReaderShared::default());
+        self.with_lock(|mut state| {
+            Self::check_not_shutting_down(&state)?;
+            // Must be exclusive
+            if !state.reading_streams.contains_key(&id) {
+                state
+                    .reading_streams
+                    .insert(id, ReadStreamSink::Async(Arc::clone(&shared)));
+                Ok(())
+            } else {
+                Err(ShellError::GenericError {
+                    error: format!("Failed to acquire reader for stream {id}"),
+                    msg: "tried to get a reader for a stream that's already being read".into(),
+                    span: None,
+                    help: Some("this may be a bug in the nu-plugin crate".into()),
+                    inner: vec![]
+                })
+            }
+        })?;
+        Ok(AsyncStreamReader::new(id, shared, writer))
+    }
+
     pub(crate) fn write_stream<W>(
         &self,
         id: StreamId,
         writer: W,
-        high_pressure_mark: i32
+        window: StreamWindowConfig
     ) -> Result<StreamWriter<W>, ShellError>
     where
         W: WriteStreamMessage,
     {
-        let signal = Arc::new(StreamWriterSignal::new(high_pressure_mark));
-        self.with_lock(|mut state| {
+        let signal = self.with_lock(|mut state| {
+            Self::check_not_shutting_down(&state)?;
             // Remove dead writing streams
             state.writing_streams.retain(|_, signal| signal.strong_count() > 0);
             // Must be exclusive
             if !state.writing_streams.contains_key(&id) {
+                let signal = Arc::new(StreamWriterSignal::new(window, state.keepalive));
                 state.writing_streams.insert(id, Arc::downgrade(&signal));
-                Ok(())
+                Ok(signal)
             } else {
                 Err(ShellError::GenericError {
                     error: format!("Failed to acquire writer for stream {id}"),
@@ -0,0 +1,46 @@
+use crate::lev_distance::levenshtein_distance;
+
+/// Suggest the closest match to `input` among `possibilities`, for a "did you mean ...?" hint on
+/// a mistyped command, flag, or column name.
+///
+/// Candidates are first filtered to a length-proportional distance threshold (roughly a third of
+/// `input`'s length) before ranking, so a handful of near-misses don't get buried under
+/// candidates that are obviously unrelated. Ties are broken by preferring whichever candidate
+/// shares the longest case-insensitive prefix with `input`, since a mistyped suffix (`"foramt"`
+/// vs `"format"`) is a far more common typo shape than a mistyped prefix.
+pub fn did_you_mean(possibilities: &[String], input: &str) -> Option<String> {
+    let threshold = (input.chars().count() / 3).max(1);
+
+    let mut best: Option<(&String, usize, usize)> = None;
+    for possibility in possibilities {
+        let distance = levenshtein_distance(possibility, input, threshold);
+        if distance > threshold {
+            continue;
+        }
+
+        let shared_prefix = shared_case_insensitive_prefix_len(possibility, input);
+
+        let is_better = match best {
+            None => true,
+            Some((_, best_distance, best_prefix)) => {
+                distance < best_distance
+                    || (distance == best_distance && shared_prefix > best_prefix)
+            }
+        };
+
+        if is_better {
+            best = Some((possibility, distance, shared_prefix));
+        }
+    }
+
+    best.map(|(possibility, _, _)| possibility.clone())
+}
+
+/// Length of the longest prefix `a` and `b` share, compared case-insensitively.
+fn shared_case_insensitive_prefix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .zip(b.chars().flat_map(char::to_lowercase))
+        .take_while(|(a, b)| a == b)
+        .count()
+}
@@ -1,3 +1,8 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
 use crate::{ast, ir, DeclId, FromValue, ShellError, Span, Value};
 
 use super::{EngineState, Stack, StateWorkingSet};
@@ -31,58 +36,94 @@ impl Call<'_> {
         }
     }
 
-    /// Assert that the call is `ast::Call`, and fail with an error if it isn't.
-    ///
-    /// Provided as a stop-gap for commands that can't work with `ir::Call`, or just haven't been
-    /// implemented yet. Eventually these issues should be resolved and then this can be removed.
-    pub fn assert_ast_call(&self) -> Result<&ast::Call, ShellError> {
-        match &self.inner {
-            CallImpl::AstRef(call) => Ok(call),
-            CallImpl::AstBox(call) => Ok(&call),
-            _ => Err(ShellError::NushellFailedSpanned {
-                msg: "Can't be used in IR context".into(),
-                label: "this command is not yet supported by IR evaluation".into(),
-                span: self.head,
-            }),
-        }
-    }
-
-    /// FIXME: implementation asserts `ast::Call` and proxies to that
+    /// Evaluator-agnostic implementation of `has_flag_const()`. Resolves the named flag against
+    /// whichever argument representation the call actually carries, const-folding the flag's
+    /// expression against `working_set` rather than running it through the main interpreter —
+    /// the same split rustc draws between a const-eval path and the full interpreter.
     pub fn has_flag_const(
         &self,
         working_set: &StateWorkingSet,
         flag_name: &str,
     ) -> Result<bool, ShellError> {
-        self.assert_ast_call()?
-            .has_flag_const(working_set, flag_name)
+        let _guard = ConstEvalGuard::enter(self)?;
+        let key = const_eval_cache_key(self, "has_flag", flag_name, TypeId::of::<bool>());
+        if let Some(cached) = const_eval_cache_get::<bool>(&key) {
+            return Ok(cached);
+        }
+        let result = match &self.inner {
+            CallImpl::AstRef(call) => call.has_flag_const(working_set, flag_name),
+            CallImpl::AstBox(call) => call.has_flag_const(working_set, flag_name),
+            CallImpl::IrRef(call) => call.has_flag_const(working_set, flag_name),
+            CallImpl::IrBox(call) => call.has_flag_const(working_set, flag_name),
+        }?;
+        const_eval_cache_put(key, result);
+        Ok(result)
     }
 
-    /// FIXME: implementation asserts `ast::Call` and proxies to that
-    pub fn get_flag_const<T: FromValue>(
+    /// Evaluator-agnostic implementation of `get_flag_const()`. See [`Self::has_flag_const`] for
+    /// how the two argument representations are reconciled.
+    pub fn get_flag_const<T: FromValue + Clone + 'static>(
         &self,
         working_set: &StateWorkingSet,
         name: &str,
     ) -> Result<Option<T>, ShellError> {
-        self.assert_ast_call()?.get_flag_const(working_set, name)
+        let _guard = ConstEvalGuard::enter(self)?;
+        let key = const_eval_cache_key(self, "get_flag", name, TypeId::of::<T>());
+        if let Some(cached) = const_eval_cache_get::<Option<T>>(&key) {
+            return Ok(cached);
+        }
+        let result = match &self.inner {
+            CallImpl::AstRef(call) => call.get_flag_const(working_set, name),
+            CallImpl::AstBox(call) => call.get_flag_const(working_set, name),
+            CallImpl::IrRef(call) => call.get_flag_const(working_set, name),
+            CallImpl::IrBox(call) => call.get_flag_const(working_set, name),
+        }?;
+        const_eval_cache_put(key, result.clone());
+        Ok(result)
     }
 
-    /// FIXME: implementation asserts `ast::Call` and proxies to that
-    pub fn req_const<T: FromValue>(
+    /// Evaluator-agnostic implementation of `req_const()`. See [`Self::has_flag_const`] for how
+    /// the two argument representations are reconciled.
+    pub fn req_const<T: FromValue + Clone + 'static>(
         &self,
         working_set: &StateWorkingSet,
         pos: usize,
     ) -> Result<T, ShellError> {
-        self.assert_ast_call()?.req_const(working_set, pos)
+        let _guard = ConstEvalGuard::enter(self)?;
+        let key = const_eval_cache_key(self, "req", &pos.to_string(), TypeId::of::<T>());
+        if let Some(cached) = const_eval_cache_get::<T>(&key) {
+            return Ok(cached);
+        }
+        let result = match &self.inner {
+            CallImpl::AstRef(call) => call.req_const(working_set, pos),
+            CallImpl::AstBox(call) => call.req_const(working_set, pos),
+            CallImpl::IrRef(call) => call.req_const(working_set, pos),
+            CallImpl::IrBox(call) => call.req_const(working_set, pos),
+        }?;
+        const_eval_cache_put(key, result.clone());
+        Ok(result)
     }
 
-    /// FIXME: implementation asserts `ast::Call` and proxies to that
-    pub fn rest_const<T: FromValue>(
+    /// Evaluator-agnostic implementation of `rest_const()`. See [`Self::has_flag_const`] for how
+    /// the two argument representations are reconciled.
+    pub fn rest_const<T: FromValue + Clone + 'static>(
         &self,
         working_set: &StateWorkingSet,
         starting_pos: usize,
     ) -> Result<Vec<T>, ShellError> {
-        self.assert_ast_call()?
-            .rest_const(working_set, starting_pos)
+        let _guard = ConstEvalGuard::enter(self)?;
+        let key = const_eval_cache_key(self, "rest", &starting_pos.to_string(), TypeId::of::<T>());
+        if let Some(cached) = const_eval_cache_get::<Vec<T>>(&key) {
+            return Ok(cached);
+        }
+        let result = match &self.inner {
+            CallImpl::AstRef(call) => call.rest_const(working_set, starting_pos),
+            CallImpl::AstBox(call) => call.rest_const(working_set, starting_pos),
+            CallImpl::IrRef(call) => call.rest_const(working_set, starting_pos),
+            CallImpl::IrBox(call) => call.rest_const(working_set, starting_pos),
+        }?;
+        const_eval_cache_put(key, result.clone());
+        Ok(result)
     }
 
     /// Returns a span covering the whole call.
@@ -97,51 +138,85 @@ impl Call<'_> {
 
     /// Evaluator-agnostic implementation of `rest_iter_flattened()`. Evaluates or gets all of the
     /// positional and spread arguments, flattens spreads, and then returns one list of values.
+    ///
+    /// `evaluator` is only consulted for the AST representation; `ir::Call` already knows how to
+    /// flatten its own rest args directly against `stack` (its arguments are register
+    /// references, not `ast::Expression`s to walk), so it's ignored in that case.
     pub fn rest_iter_flattened(
         &self,
-        engine_state: &EngineState,
         stack: &mut Stack,
-        eval_expression: fn(
-            &EngineState,
-            &mut Stack,
-            &ast::Expression,
-        ) -> Result<Value, ShellError>,
+        evaluator: &dyn Evaluator,
         starting_pos: usize,
     ) -> Result<Vec<Value>, ShellError> {
-        fn by_ast(
-            call: &ast::Call,
-            engine_state: &EngineState,
-            stack: &mut Stack,
-            eval_expression: fn(
-                &EngineState,
-                &mut Stack,
-                &ast::Expression,
-            ) -> Result<Value, ShellError>,
-            starting_pos: usize,
-        ) -> Result<Vec<Value>, ShellError> {
-            call.rest_iter_flattened(starting_pos, |expr| {
-                eval_expression(engine_state, stack, expr)
-            })
+        match &self.inner {
+            CallImpl::AstRef(call) => evaluator.eval_call_args(call, stack, starting_pos),
+            CallImpl::AstBox(call) => evaluator.eval_call_args(call, stack, starting_pos),
+            CallImpl::IrRef(call) => call.rest_iter_flattened(stack, starting_pos),
+            CallImpl::IrBox(call) => call.rest_iter_flattened(stack, starting_pos),
         }
+    }
+}
 
-        fn by_ir(
-            call: &ir::Call,
-            stack: &Stack,
-            starting_pos: usize,
-        ) -> Result<Vec<Value>, ShellError> {
-            call.rest_iter_flattened(stack, starting_pos)
-        }
+/// A backend capable of evaluating a single [`ast::Expression`] to a [`Value`]. This replaces
+/// the raw `fn(&EngineState, &mut Stack, &ast::Expression) -> Result<Value, ShellError>` pointer
+/// that used to get threaded through [`Call::rest_iter_flattened`] just so the tree-walking
+/// interpreter (which lives in `nu-engine`, not here, to avoid a dependency cycle) could be
+/// plugged in. A trait object lets the interface grow -- e.g. [`Self::eval_call_args`] -- without
+/// adding another parameter to every method that needs a backend, and lets a future backend (a
+/// caching or tracing evaluator, say) be added by implementing the trait rather than by widening
+/// a pile of branches.
+pub trait Evaluator {
+    /// Evaluate a single AST expression against `stack`.
+    fn eval_expression(
+        &self,
+        stack: &mut Stack,
+        expr: &ast::Expression,
+    ) -> Result<Value, ShellError>;
 
-        match &self.inner {
-            CallImpl::AstRef(call) => {
-                by_ast(call, engine_state, stack, eval_expression, starting_pos)
-            }
-            CallImpl::AstBox(call) => {
-                by_ast(call, engine_state, stack, eval_expression, starting_pos)
-            }
-            CallImpl::IrRef(call) => by_ir(call, stack, starting_pos),
-            CallImpl::IrBox(call) => by_ir(call, stack, starting_pos),
-        }
+    /// Evaluate every positional and spread argument of `call`, flattening spreads, starting
+    /// from `starting_pos`. The default just calls [`Self::eval_expression`] on each one.
+    fn eval_call_args(
+        &self,
+        call: &ast::Call,
+        stack: &mut Stack,
+        starting_pos: usize,
+    ) -> Result<Vec<Value>, ShellError> {
+        call.rest_iter_flattened(starting_pos, |expr| self.eval_expression(stack, expr))
+    }
+}
+
+/// The AST tree-walking [`Evaluator`]. Wraps the real expression evaluator as a plain function
+/// pointer, since it's stateless beyond the `EngineState` it closes over.
+pub struct AstEvaluator<'a> {
+    pub engine_state: &'a EngineState,
+    pub eval_expression:
+        fn(&EngineState, &mut Stack, &ast::Expression) -> Result<Value, ShellError>,
+}
+
+impl Evaluator for AstEvaluator<'_> {
+    fn eval_expression(
+        &self,
+        stack: &mut Stack,
+        expr: &ast::Expression,
+    ) -> Result<Value, ShellError> {
+        (self.eval_expression)(self.engine_state, stack, expr)
+    }
+}
+
+/// The IR backend's [`Evaluator`]. `ir::Call` flattens its own rest args directly against the
+/// `Stack` without ever needing to walk an `ast::Expression` (see [`Call::rest_iter_flattened`]),
+/// so this only exists so IR callers have something to hand in where an `Evaluator` is expected.
+pub struct IrEvaluator;
+
+impl Evaluator for IrEvaluator {
+    fn eval_expression(
+        &self,
+        _stack: &mut Stack,
+        _expr: &ast::Expression,
+    ) -> Result<Value, ShellError> {
+        Err(ShellError::NushellFailed {
+            msg: "IrEvaluator can't evaluate an ast::Expression".into(),
+        })
     }
 }
 
@@ -156,6 +231,164 @@ impl CallImpl<'_> {
     }
 }
 
+std::thread_local! {
+    /// Memoized results of previous `*_const` accessor calls within the current top-level const
+    /// evaluation, so a `Call` that gets re-resolved several times over while that evaluation is
+    /// still in progress (e.g. a recursive `const` definition, or a custom command consulting the
+    /// same flag on the same call repeatedly as it recurses) skips straight to the cached `Value`
+    /// instead of re-walking the argument expressions every time. Cleared at the end of each
+    /// top-level evaluation (see [`ConstEvalGuard`]'s `Drop`), so it never helps across separate
+    /// top-level calls - there's no way to tell from here whether two of those are resolving
+    /// against the same `StateWorkingSet` or one that merely reused the same address (see
+    /// [`const_eval_cache_key`]), so the cache only covers the span where that's known for sure.
+    static CONST_EVAL_CACHE: RefCell<HashMap<String, Box<dyn Any>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Builds the cache key for a `*_const` accessor call: which top-level const evaluation it was
+/// resolved during (see `CONST_EVAL_GENERATION`), which call (by `decl_id` and the span covering
+/// the whole call, standing in for a fingerprint of its arguments since individual argument spans
+/// aren't reachable from here), which accessor, and (since e.g. `req_const::<i64>(0)` and a
+/// hypothetical `req_const::<String>(0)` must never share a slot) which `T` it was asked to
+/// produce.
+///
+/// Earlier this keyed on `working_set`'s address instead of `CONST_EVAL_GENERATION`, on the
+/// theory that a `StateWorkingSet` is short-lived enough that moving to a new one would mean old
+/// entries just aren't looked up again. That's false: a short-lived `StateWorkingSet` gets
+/// constructed and dropped repeatedly, and nothing stops a later, unrelated one (with different
+/// definitions) from landing at the same address, at which point `const_eval_cache_get` would
+/// hand back a value resolved against stale definitions. `CONST_EVAL_GENERATION` is a real
+/// monotonically increasing counter bumped once per top-level const evaluation, so it can't
+/// collide the way a reused address can, and `ConstEvalGuard`'s `Drop` clears the whole cache once
+/// that generation ends, which also keeps it from growing for the life of the thread.
+fn const_eval_cache_key(call: &Call, accessor: &str, param: &str, type_id: TypeId) -> String {
+    let generation = CONST_EVAL_GENERATION.with(Cell::get);
+    format!(
+        "{generation}|{:?}|{:?}|{accessor}:{param}|{type_id:?}",
+        call.decl_id,
+        call.span(),
+    )
+}
+
+fn const_eval_cache_get<T: Clone + 'static>(key: &str) -> Option<T> {
+    CONST_EVAL_CACHE.with(|cache| {
+        cache
+            .borrow()
+            .get(key)
+            .and_then(|cached| cached.downcast_ref::<T>())
+            .cloned()
+    })
+}
+
+fn const_eval_cache_put<T: Clone + 'static>(key: String, value: T) {
+    CONST_EVAL_CACHE.with(|cache| {
+        cache.borrow_mut().insert(key, Box::new(value));
+    });
+}
+
+std::thread_local! {
+    /// The `(head span, decl_id)` of every `*_const` accessor call currently on this thread's
+    /// stack, innermost last. A recursive `const` definition or custom command shows up here as
+    /// the same frame (or a small repeating cycle of frames) appended over and over.
+    static CONST_EVAL_FRAMES: RefCell<Vec<(Span, DeclId)>> = const { RefCell::new(Vec::new()) };
+    /// Steps taken in the current top-level const evaluation; reset to zero once the frame stack
+    /// empties back out, so unrelated evaluations don't share a budget.
+    static CONST_EVAL_STEPS: Cell<u64> = const { Cell::new(0) };
+    static CONST_EVAL_LAST_SNAPSHOT: Cell<Option<u64>> = const { Cell::new(None) };
+    /// Bumped every time the frame stack goes from empty to non-empty, i.e. once per top-level
+    /// const evaluation. Stands in for a `StateWorkingSet`'s identity in [`const_eval_cache_key`]
+    /// -- see there for why its address can't be used for that instead.
+    static CONST_EVAL_GENERATION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Below this many steps, we don't even look at the frame stack: this keeps the overwhelming
+/// majority of const evaluations, which are a handful of calls deep at most, completely free.
+const CONST_EVAL_STEP_THRESHOLD: u64 = 1_000_000;
+
+/// Detects a parse-time constant evaluation that loops forever instead of making progress (e.g.
+/// a `const` definition that recurses on itself, or a custom command that does while being
+/// const-folded). Modeled on the classic interpreter loop guard: count steps for free, and only
+/// once the count crosses [`CONST_EVAL_STEP_THRESHOLD`] start taking cheap structural snapshots
+/// of the part of the evaluator state that matters for progress -- here, the stack of active
+/// `Call` heads/`decl_id`s -- every power-of-two step. Two consecutive snapshots being identical
+/// means nothing has changed in all that time, so we give up rather than hang the shell forever.
+struct ConstEvalGuard;
+
+impl ConstEvalGuard {
+    fn enter(call: &Call) -> Result<ConstEvalGuard, ShellError> {
+        CONST_EVAL_FRAMES.with(|frames| {
+            let mut frames = frames.borrow_mut();
+            if frames.is_empty() {
+                CONST_EVAL_GENERATION.with(|generation| generation.set(generation.get() + 1));
+            }
+            frames.push((call.head, call.decl_id));
+        });
+        match Self::check_progress(call) {
+            Ok(()) => Ok(ConstEvalGuard),
+            Err(err) => {
+                CONST_EVAL_FRAMES.with(|frames| {
+                    frames.borrow_mut().pop();
+                });
+                Err(err)
+            }
+        }
+    }
+
+    fn check_progress(call: &Call) -> Result<(), ShellError> {
+        let step = CONST_EVAL_STEPS.with(|steps| {
+            let next = steps.get() + 1;
+            steps.set(next);
+            next
+        });
+        if step <= CONST_EVAL_STEP_THRESHOLD || !step.is_power_of_two() {
+            return Ok(());
+        }
+        // `Span`/`DeclId` aren't `Hash`, but everything in this file already relies on them being
+        // `Debug` (via `#[derive(Debug)]` on `Call`/`CallImpl`), so snapshot via their debug
+        // representation instead of requiring a new trait bound.
+        let snapshot = CONST_EVAL_FRAMES.with(|frames| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            format!("{:?}", frames.borrow()).hash(&mut hasher);
+            hasher.finish()
+        });
+        let stalled =
+            CONST_EVAL_LAST_SNAPSHOT.with(|last| last.replace(Some(snapshot)) == Some(snapshot));
+        if stalled {
+            Err(ShellError::GenericError {
+                error: "Constant evaluation did not terminate".into(),
+                msg: format!(
+                    "this call has been const-evaluated for over {step} steps without making progress"
+                ),
+                span: Some(call.span()),
+                help: Some(
+                    "this is usually caused by a `const` definition or custom command that recurses on itself".into()
+                ),
+                inner: vec![],
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for ConstEvalGuard {
+    fn drop(&mut self) {
+        CONST_EVAL_FRAMES.with(|frames| {
+            let mut frames = frames.borrow_mut();
+            frames.pop();
+            if frames.is_empty() {
+                // Back at the top level: steps/snapshots from here on belong to whatever const
+                // evaluation comes next, not this one.
+                CONST_EVAL_STEPS.with(|steps| steps.set(0));
+                CONST_EVAL_LAST_SNAPSHOT.with(|last| last.set(None));
+                // Also drop every cache entry from the generation that just ended, rather than
+                // letting them sit around keyed by a generation nothing will ever look up again.
+                CONST_EVAL_CACHE.with(|cache| cache.borrow_mut().clear());
+            }
+        });
+    }
+}
+
 impl<'a> From<&'a ast::Call> for Call<'a> {
     fn from(call: &'a ast::Call) -> Self {
         Call {
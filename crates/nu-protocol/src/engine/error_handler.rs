@@ -1,6 +1,8 @@
-use crate::{record, ShellError, Value, VarId};
+use crate::{format_error, record, ShellError, Span, Value, VarId};
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
 
-use super::{EngineState, Stack};
+use super::{EngineState, Stack, StateWorkingSet};
 
 /// Describes an error handler stored during IR evaluation.
 #[derive(Debug, Clone, Copy)]
@@ -20,6 +22,14 @@ impl ErrorHandler {
                 record! {
                     "msg" => Value::string(format!("{}", error), span),
                     "debug" => Value::string(format!("{:?}", error), span),
+                    "span" => primary_span(&error)
+                        .map(|s| span_to_value(s, span))
+                        .unwrap_or(Value::nothing(span)),
+                    "labels" => Value::list(labels_to_values(&error, span), span),
+                    "exit_code" => exit_code_of(&error)
+                        .map(|code| Value::int(code, span))
+                        .unwrap_or(Value::nothing(span)),
+                    "inner" => Value::list(inner_chain_to_values(&error, span), span),
                     "raw" => Value::error(error, span),
                 },
                 span,
@@ -29,6 +39,82 @@ impl ErrorHandler {
     }
 }
 
+/// Turn a byte-offset [`miette::SourceSpan`] from a diagnostic label into a nushell [`Span`].
+/// This is the best-effort conversion available - labels are attached relative to whatever
+/// source map miette rendered against, which doesn't always line up 1:1 with a [`Span`] cut from
+/// the original source, but it's the same information the pretty-printed error report uses.
+fn label_span(span: &miette::SourceSpan) -> Span {
+    Span::new(span.offset(), span.offset() + span.len())
+}
+
+/// Render a [`Span`] as a `{start, end}` record, the same shape `metadata`/`debug` commands use
+/// elsewhere for exposing a span to user-level code.
+fn span_to_value(span: Span, call_span: Span) -> Value {
+    Value::record(
+        record! {
+            "start" => Value::int(span.start as i64, call_span),
+            "end" => Value::int(span.end as i64, call_span),
+        },
+        call_span,
+    )
+}
+
+/// The error's own primary span, taken from its first diagnostic label if it has one.
+fn primary_span(error: &ShellError) -> Option<Span> {
+    error
+        .labels()
+        .and_then(|mut labels| labels.next())
+        .map(|label| label_span(label.inner()))
+}
+
+/// Every diagnostic label on `error`, as `{text, span}` records, so a catch block can walk the
+/// full set rather than just the primary one.
+fn labels_to_values(error: &ShellError, span: Span) -> Vec<Value> {
+    error
+        .labels()
+        .into_iter()
+        .flatten()
+        .map(|label| {
+            Value::record(
+                record! {
+                    "text" => Value::string(label.label().unwrap_or("").to_string(), span),
+                    "span" => span_to_value(label_span(label.inner()), span),
+                },
+                span,
+            )
+        })
+        .collect()
+}
+
+/// The exit code carried by the error, for external-command failures that have one.
+///
+/// There's currently no variant of `ShellError` to match on here for this - the exit code would
+/// live on a specific variant (e.g. one raised when an external command exits non-zero), not
+/// something the generic `Diagnostic` interface exposes. This always returns `None` until such a
+/// variant is matched here explicitly.
+fn exit_code_of(_error: &ShellError) -> Option<i64> {
+    None
+}
+
+/// Walk the chain of nested/source diagnostics (e.g. an error that wraps the one that caused it)
+/// and render each as a `{msg, debug}` record, so a catch block can inspect the whole chain
+/// rather than just the outermost error's message.
+fn inner_chain_to_values(error: &ShellError, span: Span) -> Vec<Value> {
+    let mut inner = Vec::new();
+    let mut current = error.diagnostic_source();
+    while let Some(source) = current {
+        inner.push(Value::record(
+            record! {
+                "msg" => Value::string(format!("{source}"), span),
+                "debug" => Value::string(format!("{source:?}"), span),
+            },
+            span,
+        ));
+        current = source.diagnostic_source();
+    }
+    inner
+}
+
 /// Keeps track of error handlers pushed during evaluation of an IR block.
 #[derive(Debug, Clone)]
 pub struct ErrorHandlerStack {
@@ -72,4 +158,174 @@ impl ErrorHandlerStack {
             )
         }
     }
+
+    /// Resolve what a catch block's own result means for error propagation: if it returned the
+    /// `raw` error value unchanged - a plain rethrow, e.g. `catch { |err| $err.raw }` - propagate
+    /// the original [`ShellError`] rather than wrapping a new generic error around the rendered
+    /// catch output, so the caller can still match on its original variant. Otherwise, wrap
+    /// `catch_result` as a new error to propagate, since the catch block chose to return
+    /// something else instead of handling the error.
+    pub fn resolve_catch_result(original: ShellError, catch_result: Value) -> ShellError {
+        match &catch_result {
+            Value::Error { error, .. } if error_eq(error, &original) => original,
+            _ => {
+                let span = catch_result.span();
+                ShellError::GenericError {
+                    error: "Error in catch block".into(),
+                    msg: format!("{catch_result:?}"),
+                    span: Some(span),
+                    help: None,
+                    inner: vec![original],
+                }
+            }
+        }
+    }
+}
+
+/// Best-effort equality between two [`ShellError`]s for detecting a plain rethrow: there's no
+/// `PartialEq` impl on `ShellError` to rely on, so this compares the debug-formatted output,
+/// which is stable for a value that was never modified between being captured and returned.
+fn error_eq(a: &ShellError, b: &ShellError) -> bool {
+    format!("{a:?}") == format!("{b:?}")
+}
+
+/// Severity of a [`JsonDiagnostic`] or one of its `children`, mirroring the levels
+/// `rustc --error-format=json` uses so editor tooling that already knows how to read those can
+/// read these too. Also doubles as the severity a plugin reports via
+/// `EngineCall::ReportDiagnostic`, since the two are the same concept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonDiagnosticLevel {
+    Error,
+    Warning,
+    Help,
+    Note,
+}
+
+/// One labeled byte range within a [`JsonDiagnostic`], with its position already resolved to
+/// 1-indexed line/column as well as the raw byte offsets, so a consumer doesn't have to re-derive
+/// either from the other.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonSpan {
+    pub file_name: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    pub is_primary: bool,
+    pub label: Option<String>,
+}
+
+/// A structured, serializable view of a [`ShellError`] (or one of its nested causes/labels),
+/// shaped like a single entry of `rustc --error-format=json` output, so editors and linters can
+/// build quickfix lists without screen-scraping the human-rendered report.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonDiagnostic {
+    pub message: String,
+    pub level: JsonDiagnosticLevel,
+    pub spans: Vec<JsonSpan>,
+    pub children: Vec<JsonDiagnostic>,
+    pub rendered: String,
+}
+
+impl JsonDiagnostic {
+    /// Build a [`JsonDiagnostic`] for `error`, resolving every [`Span`] in its labels against
+    /// `working_set` to compute line/column from byte offsets. Each nested
+    /// [`Diagnostic::diagnostic_source`] becomes a `children` entry at [`JsonDiagnosticLevel::Note`],
+    /// matching how [`inner_chain_to_values`] already walks that same chain for the `catch`
+    /// record above.
+    pub fn from_shell_error(error: &ShellError, working_set: &StateWorkingSet) -> JsonDiagnostic {
+        let spans = error
+            .labels()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, label)| {
+                json_span(
+                    working_set,
+                    label_span(label.inner()),
+                    i == 0,
+                    label.label().map(|s| s.to_string()),
+                )
+            })
+            .collect();
+
+        let children = diagnostic_chain(error)
+            .map(|source| JsonDiagnostic {
+                message: format!("{source}"),
+                level: JsonDiagnosticLevel::Note,
+                spans: vec![],
+                children: vec![],
+                rendered: format!("{source}"),
+            })
+            .collect();
+
+        JsonDiagnostic {
+            message: format!("{error}"),
+            level: JsonDiagnosticLevel::Error,
+            spans,
+            children,
+            rendered: format_error(working_set, error),
+        }
+    }
+}
+
+/// Resolve `span` against `working_set`'s source to a [`JsonSpan`], computing 1-indexed
+/// line/column from the byte offset. Counts Unicode scalar values rather than bytes for the
+/// column, so a span that starts partway through a multi-byte UTF-8 character still lands on a
+/// sensible column instead of a byte index that could split one.
+fn json_span(
+    working_set: &StateWorkingSet,
+    span: Span,
+    is_primary: bool,
+    label: Option<String>,
+) -> JsonSpan {
+    let (line_start, column_start) = line_and_column(working_set, span.start);
+    let (line_end, column_end) = line_and_column(working_set, span.end);
+
+    JsonSpan {
+        file_name: working_set
+            .get_filename(span.start)
+            .unwrap_or_else(|| "source".into()),
+        byte_start: span.start,
+        byte_end: span.end,
+        line_start,
+        line_end,
+        column_start,
+        column_end,
+        is_primary,
+        label,
+    }
+}
+
+/// 1-indexed (line, column) for the byte offset `at`, counting newlines and Unicode scalar values
+/// in the source preceding it. `working_set`'s spans are byte offsets into a virtual
+/// concatenation of every loaded file's contents, so counting from the very start of that
+/// concatenation is enough - there's no need to know which file `at` falls into to get its
+/// position within that file, only the text between the start of that file and `at`, which a
+/// preceding newline count already isolates.
+fn line_and_column(working_set: &StateWorkingSet, at: usize) -> (usize, usize) {
+    let preceding = working_set.get_span_contents(Span::new(0, at));
+    let text = String::from_utf8_lossy(preceding);
+
+    let line = text.matches('\n').count() + 1;
+    let column = match text.rfind('\n') {
+        Some(last_newline_byte) => text[last_newline_byte + 1..].chars().count() + 1,
+        None => text.chars().count() + 1,
+    };
+
+    (line, column)
+}
+
+/// Walk the chain of nested/source diagnostics, same traversal as [`inner_chain_to_values`] above,
+/// but yielding the [`Diagnostic`]s themselves rather than pre-rendered [`Value`]s.
+fn diagnostic_chain(error: &ShellError) -> impl Iterator<Item = &dyn Diagnostic> {
+    let mut current = error.diagnostic_source();
+    std::iter::from_fn(move || {
+        let source = current?;
+        current = source.diagnostic_source();
+        Some(source)
+    })
 }
@@ -0,0 +1,54 @@
+//! Edit-distance utilities backing [`crate::did_you_mean`]'s typo suggestions.
+
+/// Optimal String Alignment (Damerau-Levenshtein) distance between `a` and `b`: the minimum
+/// number of single-character insertions, deletions, substitutions, or adjacent transpositions
+/// needed to turn `a` into `b`. Unlike plain Levenshtein distance, a single swapped pair of
+/// adjacent characters (`"lnies"` -> `"lines"`) counts as distance 1, not 2, which matches how a
+/// human would judge the typo.
+///
+/// Gives up early and returns `max + 1` once it's clear the true distance exceeds `max`, so a
+/// caller ranking many candidates against a length-proportional cutoff doesn't pay for the full
+/// DP matrix on candidates that are obviously too different.
+pub fn levenshtein_distance(a: &str, b: &str, max: usize) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (m, n) = (a.len(), b.len());
+
+    if m.abs_diff(n) > max {
+        return max + 1;
+    }
+
+    // d[i][j] holds the edit distance between a[..i] and b[..j].
+    let mut d = vec![vec![0usize; n + 1]; m + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..=n {
+        d[0][j] = j;
+    }
+
+    for i in 1..=m {
+        let mut row_min = usize::MAX;
+        for j in 1..=n {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut value = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+
+            // A single transposition of the two preceding characters also reaches this cell.
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                value = value.min(d[i - 2][j - 2] + 1);
+            }
+
+            d[i][j] = value;
+            row_min = row_min.min(value);
+        }
+        // Edit distance can only grow as more of `a` is consumed, so once an entire row is
+        // already over `max`, every later row will be too.
+        if row_min > max {
+            return max + 1;
+        }
+    }
+
+    d[m][n]
+}
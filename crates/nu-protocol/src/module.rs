@@ -0,0 +1,118 @@
+//! Canonical, fully-qualified paths for modules and the commands/aliases they export, mirroring
+//! how Rust's `module_path!` yields `crate::foo::bar::baz`.
+//!
+//! The real module/scope system that registers a `Module` against the parser's declaration table
+//! lives in the parser and working-set types, which aren't part of this crate in this checkout,
+//! so the types here model the namespace/path bookkeeping on its own: a [`Module`] records its
+//! own name and its parent chain at construction time, and [`ModuleRegistry`] is what a `DeclId`
+//! -> path lookup would be backed by once it's wired into that registration point.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A module's position in the namespace, as the dotted path a user would write to refer to it
+/// (e.g. `std.math`), or to something it exports (e.g. `std.math.abs`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModulePath(Vec<String>);
+
+impl ModulePath {
+    /// The path of the top-level namespace, with nothing nested under it yet.
+    pub fn root() -> ModulePath {
+        ModulePath(Vec::new())
+    }
+
+    /// The path of a submodule named `name` nested directly under this one.
+    pub fn join(&self, name: impl Into<String>) -> ModulePath {
+        let mut segments = self.0.clone();
+        segments.push(name.into());
+        ModulePath(segments)
+    }
+
+    /// The dotted path a user would type to refer to this module, e.g. `std.math`. Empty for the
+    /// root.
+    pub fn dotted(&self) -> String {
+        self.0.join(".")
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ModulePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.dotted())
+    }
+}
+
+/// A namespace: a name plus the chain of parent modules it's nested under, recorded at
+/// registration time so its fully-qualified path doesn't have to be re-derived by walking some
+/// other structure later.
+#[derive(Debug, Clone)]
+pub struct Module {
+    name: String,
+    parent: ModulePath,
+}
+
+impl Module {
+    /// Register a new module named `name` directly under `parent`.
+    pub fn new(name: impl Into<String>, parent: ModulePath) -> Module {
+        Module {
+            name: name.into(),
+            parent,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// This module's own fully-qualified path, e.g. `std.math` for a `math` module registered
+    /// under the `std` parent.
+    pub fn qualified_name(&self) -> ModulePath {
+        self.parent.join(self.name.clone())
+    }
+}
+
+/// Maps a command/alias id (`DeclId` in the real working set - generic here since that type isn't
+/// part of this crate in this checkout) to the [`ModulePath`] of the module that originally
+/// defined it, so a re-export through `export use` resolves back to where a command was actually
+/// declared rather than the intermediary module it was re-exported through.
+///
+/// This is the piece a `DeclId -> Vec<String>` lookup would be backed by: the parser would call
+/// [`ModuleRegistry::register`] once, at the point a command is first declared inside a module
+/// (never again when it's re-exported elsewhere), and callers like `which`/`scope modules` would
+/// call [`ModuleRegistry::path_of`] to disambiguate identically-named commands imported from
+/// different nested submodules.
+#[derive(Debug)]
+pub struct ModuleRegistry<DeclId: Eq + Hash> {
+    owning_module: HashMap<DeclId, ModulePath>,
+}
+
+impl<DeclId: Eq + Hash> Default for ModuleRegistry<DeclId> {
+    fn default() -> Self {
+        ModuleRegistry {
+            owning_module: HashMap::new(),
+        }
+    }
+}
+
+impl<DeclId: Eq + Hash> ModuleRegistry<DeclId> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `decl` was declared inside `module`. Re-exporting `decl` elsewhere via
+    /// `export use` should *not* call this again - the first registration is the one that counts.
+    pub fn register(&mut self, decl: DeclId, module: ModulePath) {
+        self.owning_module.entry(decl).or_insert(module);
+    }
+
+    /// The full namespace path of whatever module originally declared `decl`, e.g.
+    /// `["std", "math"]` for a command registered in `std.math`.
+    pub fn path_of(&self, decl: &DeclId) -> Option<Vec<String>> {
+        self.owning_module
+            .get(decl)
+            .map(|path| path.segments().to_vec())
+    }
+}
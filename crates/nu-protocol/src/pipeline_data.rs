@@ -4,9 +4,15 @@ use crate::{
     format_error, Config, ListStream, RawStream, ShellError, Span, Value,
 };
 use nu_utils::{stderr_write_all_and_flush, stdout_write_all_and_flush};
-use std::sync::{atomic::AtomicBool, Arc};
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
+use std::sync::{atomic::AtomicBool, Arc, Mutex};
 use std::thread;
-use std::{io::Write, path::PathBuf};
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+};
 
 const LINE_ENDING_PATTERN: &[char] = &['\r', '\n'];
 
@@ -50,22 +56,107 @@ pub enum PipelineData {
         span: Span,
         metadata: Option<PipelineMetadata>,
         trim_end_newline: bool,
+        stdout_type: ByteStreamType,
+        /// The producing external command's raw stdout file descriptor, present only when
+        /// nothing between it and whatever consumes this pipeline needs to observe the
+        /// bytes. `run-external` can hand this straight to the next external command's
+        /// stdin and skip reading through `stdout`'s `RawStream` entirely. This is purely an
+        /// optional fast-path hint: a builtin, redirection, or any `stdout`-reading consumer
+        /// (`into_value`, `map`, ...) ignores it and falls back to `stdout` as before, and
+        /// dropping it (e.g. on Ctrl-C) closes the fd.
+        #[cfg(unix)]
+        raw_fd: Option<OwnedFd>,
     },
     Empty,
 }
 
-#[derive(Debug, Clone)]
+/// How the bytes on an external command's stdout stream should be interpreted once they
+/// need to collapse into a single `Value`.
+///
+/// Before this existed, `into_value`, `map`, `flat_map`, `filter` and `collect_string` each
+/// guessed independently (and disagreed) about whether a stream's bytes were text or binary.
+/// Tagging the stream with its known type lets [`collapse_bytes`] make that call exactly once,
+/// consistently, for all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ByteStreamType {
+    /// The stream is known to only ever produce valid UTF-8 text.
+    String,
+    /// The stream is known to produce raw bytes; never attempt to coerce it into a string,
+    /// even if it happens to be valid UTF-8.
+    Binary,
+    /// The stream's contents haven't been classified. Sniff it for valid UTF-8 the one time
+    /// it's collapsed into a `Value`.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineMetadata {
     pub data_source: DataSource,
+    /// A MIME-style hint (e.g. `application/json`) for the shape of the data this pipeline
+    /// carries, so a later `to`/`save`-style command doesn't have to guess. Nothing in this
+    /// file sets it (no byte-stream variant here knows its own content type); it is filled in
+    /// from outside, e.g. by a plugin tagging its output's existing metadata, and commands that
+    /// already thread a `PipelineMetadata` through, like `collect`, carry it along unchanged.
+    pub content_type: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DataSource {
     Ls,
     HtmlThemes,
     FilePath(PathBuf),
 }
 
+/// A first-class handle for interruption, replacing the bare `Option<Arc<AtomicBool>>` that used
+/// to get threaded individually through `ListStream`, `RawStream`, and every iterator adapter on
+/// `PipelineData`.
+///
+/// Today this only carries Ctrl-C, but keeping it behind one type leaves room to add further
+/// signals (a SIGTERM/reset flag, say) without revisiting every signature that takes one again.
+#[derive(Debug, Clone, Default)]
+pub struct Signals {
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl Signals {
+    /// A `Signals` that never reports interrupted, for contexts with no interrupt source to
+    /// watch (tests, or a stream that's known to run to completion on its own).
+    pub fn empty() -> Signals {
+        Signals { interrupt: None }
+    }
+
+    /// Whether the underlying flag has been set.
+    pub fn interrupted(&self) -> bool {
+        self.interrupt
+            .as_deref()
+            .is_some_and(|flag| flag.load(std::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// Check for interruption, producing a real `ShellError::Interrupted` at `span` instead of
+    /// silently stopping, so a tight loop has one place to bail out of with a proper error.
+    pub fn check(&self, span: Span) -> Result<(), ShellError> {
+        if self.interrupted() {
+            Err(ShellError::Interrupted { span })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl From<Arc<AtomicBool>> for Signals {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Signals {
+            interrupt: Some(flag),
+        }
+    }
+}
+
+impl From<Option<Arc<AtomicBool>>> for Signals {
+    fn from(flag: Option<Arc<AtomicBool>>) -> Self {
+        Signals { interrupt: flag }
+    }
+}
+
 impl PipelineData {
     pub fn new_with_metadata(metadata: Option<PipelineMetadata>, span: Span) -> PipelineData {
         PipelineData::Value(Value::nothing(span), metadata)
@@ -85,6 +176,9 @@ impl PipelineData {
             span: Span::unknown(),
             metadata: None,
             trim_end_newline: false,
+            stdout_type: ByteStreamType::Unknown,
+            #[cfg(unix)]
+            raw_fd: None,
         }
     }
 
@@ -112,6 +206,29 @@ impl PipelineData {
         self
     }
 
+    /// Attach the raw stdout file descriptor of the external command producing this stream,
+    /// for `run-external` to hand straight to a piped-into external's stdin. A no-op on any
+    /// other `PipelineData` variant.
+    #[cfg(unix)]
+    pub fn with_stdout_fd(mut self, fd: OwnedFd) -> Self {
+        if let PipelineData::ExternalStream { raw_fd, .. } = &mut self {
+            *raw_fd = Some(fd);
+        }
+
+        self
+    }
+
+    /// Take the raw stdout file descriptor fast-path hint, if this is an `ExternalStream`
+    /// carrying one. Returns `None` for every other pipeline shape, or if nothing captured
+    /// one (or it was already taken).
+    #[cfg(unix)]
+    pub fn take_stdout_fd(&mut self) -> Option<OwnedFd> {
+        match self {
+            PipelineData::ExternalStream { raw_fd, .. } => raw_fd.take(),
+            _ => None,
+        }
+    }
+
     pub fn is_nothing(&self) -> bool {
         matches!(self, PipelineData::Value(Value::Nothing { .. }, ..))
             || matches!(self, PipelineData::Empty)
@@ -148,64 +265,18 @@ impl PipelineData {
                 Value::nothing(span)
             }
             PipelineData::ExternalStream {
-                stdout: Some(mut s),
+                stdout: Some(s),
                 exit_code,
                 trim_end_newline,
+                stdout_type,
                 ..
             } => {
-                let mut items = vec![];
-
-                for val in &mut s {
-                    match val {
-                        Ok(val) => {
-                            items.push(val);
-                        }
-                        Err(e) => {
-                            return Value::error(e, span);
-                        }
-                    }
-                }
-
                 // Make sure everything has finished
                 if let Some(exit_code) = exit_code {
                     let _: Vec<_> = exit_code.into_iter().collect();
                 }
 
-                // NOTE: currently trim-end-newline only handles for string output.
-                // For binary, user might need origin data.
-                if s.is_binary {
-                    let mut output = vec![];
-                    for item in items {
-                        match item.coerce_into_binary() {
-                            Ok(item) => {
-                                output.extend(item);
-                            }
-                            Err(err) => {
-                                return Value::error(err, span);
-                            }
-                        }
-                    }
-
-                    Value::binary(
-                        output, span, // FIXME?
-                    )
-                } else {
-                    let mut output = String::new();
-                    for item in items {
-                        match item.coerce_into_string() {
-                            Ok(s) => output.push_str(&s),
-                            Err(err) => {
-                                return Value::error(err, span);
-                            }
-                        }
-                    }
-                    if trim_end_newline {
-                        output.truncate(output.trim_end_matches(LINE_ENDING_PATTERN).len())
-                    }
-                    Value::string(
-                        output, span, // FIXME?
-                    )
-                }
+                collapse_bytes(s, stdout_type, trim_end_newline)
             }
         }
     }
@@ -253,8 +324,8 @@ impl PipelineData {
                 }
 
                 if let Some(exit_code) = exit_code {
-                    let result = drain_exit_code(exit_code)?;
-                    Ok(result)
+                    let status = drain_exit_code(exit_code)?;
+                    Ok(status.code())
                 } else {
                     Ok(0)
                 }
@@ -306,11 +377,11 @@ impl PipelineData {
         }
     }
 
-    pub fn into_interruptible_iter(self, ctrlc: Option<Arc<AtomicBool>>) -> PipelineIterator {
+    pub fn into_interruptible_iter(self, signals: impl Into<Signals>) -> PipelineIterator {
         let mut iter = self.into_iter();
 
         if let PipelineIterator(PipelineData::ListStream(s, ..)) = &mut iter {
-            s.ctrlc = ctrlc;
+            s.signals = signals.into();
         }
 
         iter
@@ -325,18 +396,12 @@ impl PipelineData {
             PipelineData::ExternalStream {
                 stdout: Some(s),
                 trim_end_newline,
+                stdout_type,
                 ..
-            } => {
-                let mut output = String::new();
-
-                for val in s {
-                    output.push_str(&val?.coerce_into_string()?);
-                }
-                if trim_end_newline {
-                    output.truncate(output.trim_end_matches(LINE_ENDING_PATTERN).len());
-                }
-                Ok(output)
-            }
+            } => match collapse_bytes(s, stdout_type, trim_end_newline) {
+                Value::Error { error, .. } => Err(*error),
+                v => Ok(v.to_expanded_string(separator, config)),
+            },
         }
     }
 
@@ -414,43 +479,33 @@ impl PipelineData {
     }
 
     /// Simplified mapper to help with simple values also. For full iterator support use `.into_iter()` instead
-    pub fn map<F>(
-        self,
-        mut f: F,
-        ctrlc: Option<Arc<AtomicBool>>,
-    ) -> Result<PipelineData, ShellError>
+    pub fn map<F>(self, mut f: F, signals: impl Into<Signals>) -> Result<PipelineData, ShellError>
     where
         Self: Sized,
         F: FnMut(Value) -> Value + 'static + Send,
     {
+        let signals = signals.into();
         match self {
             PipelineData::Value(Value::List { vals, .. }, ..) => {
-                Ok(vals.into_iter().map(f).into_pipeline_data(ctrlc))
+                Ok(vals.into_iter().map(f).into_pipeline_data(signals))
             }
             PipelineData::Empty => Ok(PipelineData::Empty),
-            PipelineData::ListStream(stream, ..) => Ok(stream.map(f).into_pipeline_data(ctrlc)),
+            PipelineData::ListStream(stream, ..) => Ok(stream.map(f).into_pipeline_data(signals)),
             PipelineData::ExternalStream { stdout: None, .. } => Ok(PipelineData::empty()),
             PipelineData::ExternalStream {
                 stdout: Some(stream),
                 trim_end_newline,
+                stdout_type,
                 ..
-            } => {
-                let collected = stream.into_bytes()?;
-
-                if let Ok(mut st) = String::from_utf8(collected.clone().item) {
-                    if trim_end_newline {
-                        st.truncate(st.trim_end_matches(LINE_ENDING_PATTERN).len());
-                    }
-                    Ok(f(Value::string(st, collected.span)).into_pipeline_data())
-                } else {
-                    Ok(f(Value::binary(collected.item, collected.span)).into_pipeline_data())
-                }
-            }
+            } => match collapse_bytes(stream, stdout_type, trim_end_newline) {
+                Value::Error { error, .. } => Err(*error),
+                v => Ok(f(v).into_pipeline_data()),
+            },
 
             PipelineData::Value(Value::Range { val, .. }, ..) => Ok(val
-                .into_range_iter(ctrlc.clone())?
+                .into_range_iter(signals.clone())?
                 .map(f)
-                .into_pipeline_data(ctrlc)),
+                .into_pipeline_data(signals)),
             PipelineData::Value(v, ..) => match f(v) {
                 Value::Error { error, .. } => Err(*error),
                 v => Ok(v.into_pipeline_data()),
@@ -462,7 +517,7 @@ impl PipelineData {
     pub fn flat_map<U: 'static, F>(
         self,
         mut f: F,
-        ctrlc: Option<Arc<AtomicBool>>,
+        signals: impl Into<Signals>,
     ) -> Result<PipelineData, ShellError>
     where
         Self: Sized,
@@ -470,91 +525,72 @@ impl PipelineData {
         <U as IntoIterator>::IntoIter: 'static + Send,
         F: FnMut(Value) -> U + 'static + Send,
     {
+        let signals = signals.into();
         match self {
             PipelineData::Empty => Ok(PipelineData::Empty),
             PipelineData::Value(Value::List { vals, .. }, ..) => {
-                Ok(vals.into_iter().flat_map(f).into_pipeline_data(ctrlc))
+                Ok(vals.into_iter().flat_map(f).into_pipeline_data(signals))
             }
             PipelineData::ListStream(stream, ..) => {
-                Ok(stream.flat_map(f).into_pipeline_data(ctrlc))
+                Ok(stream.flat_map(f).into_pipeline_data(signals))
             }
             PipelineData::ExternalStream { stdout: None, .. } => Ok(PipelineData::Empty),
             PipelineData::ExternalStream {
                 stdout: Some(stream),
                 trim_end_newline,
+                stdout_type,
                 ..
-            } => {
-                let collected = stream.into_bytes()?;
-
-                if let Ok(mut st) = String::from_utf8(collected.clone().item) {
-                    if trim_end_newline {
-                        st.truncate(st.trim_end_matches(LINE_ENDING_PATTERN).len())
-                    }
-                    Ok(f(Value::string(st, collected.span))
-                        .into_iter()
-                        .into_pipeline_data(ctrlc))
-                } else {
-                    Ok(f(Value::binary(collected.item, collected.span))
-                        .into_iter()
-                        .into_pipeline_data(ctrlc))
-                }
-            }
+            } => match collapse_bytes(stream, stdout_type, trim_end_newline) {
+                Value::Error { error, .. } => Err(*error),
+                v => Ok(f(v).into_iter().into_pipeline_data(signals)),
+            },
             PipelineData::Value(Value::Range { val, .. }, ..) => Ok(val
-                .into_range_iter(ctrlc.clone())?
+                .into_range_iter(signals.clone())?
                 .flat_map(f)
-                .into_pipeline_data(ctrlc)),
-            PipelineData::Value(v, ..) => Ok(f(v).into_iter().into_pipeline_data(ctrlc)),
+                .into_pipeline_data(signals)),
+            PipelineData::Value(v, ..) => Ok(f(v).into_iter().into_pipeline_data(signals)),
         }
     }
 
     pub fn filter<F>(
         self,
         mut f: F,
-        ctrlc: Option<Arc<AtomicBool>>,
+        signals: impl Into<Signals>,
     ) -> Result<PipelineData, ShellError>
     where
         Self: Sized,
         F: FnMut(&Value) -> bool + 'static + Send,
     {
+        let signals = signals.into();
         match self {
             PipelineData::Empty => Ok(PipelineData::Empty),
             PipelineData::Value(Value::List { vals, .. }, ..) => {
-                Ok(vals.into_iter().filter(f).into_pipeline_data(ctrlc))
+                Ok(vals.into_iter().filter(f).into_pipeline_data(signals))
+            }
+            PipelineData::ListStream(stream, ..) => {
+                Ok(stream.filter(f).into_pipeline_data(signals))
             }
-            PipelineData::ListStream(stream, ..) => Ok(stream.filter(f).into_pipeline_data(ctrlc)),
             PipelineData::ExternalStream { stdout: None, .. } => Ok(PipelineData::Empty),
             PipelineData::ExternalStream {
                 stdout: Some(stream),
                 trim_end_newline,
+                stdout_type,
                 ..
-            } => {
-                let collected = stream.into_bytes()?;
-
-                if let Ok(mut st) = String::from_utf8(collected.clone().item) {
-                    if trim_end_newline {
-                        st.truncate(st.trim_end_matches(LINE_ENDING_PATTERN).len())
-                    }
-                    let v = Value::string(st, collected.span);
-
-                    if f(&v) {
-                        Ok(v.into_pipeline_data())
-                    } else {
-                        Ok(PipelineData::new_with_metadata(None, collected.span))
-                    }
-                } else {
-                    let v = Value::binary(collected.item, collected.span);
-
+            } => match collapse_bytes(stream, stdout_type, trim_end_newline) {
+                Value::Error { error, .. } => Err(*error),
+                v => {
+                    let span = v.span();
                     if f(&v) {
                         Ok(v.into_pipeline_data())
                     } else {
-                        Ok(PipelineData::new_with_metadata(None, collected.span))
+                        Ok(PipelineData::new_with_metadata(None, span))
                     }
                 }
-            }
+            },
             PipelineData::Value(Value::Range { val, .. }, ..) => Ok(val
-                .into_range_iter(ctrlc.clone())?
+                .into_range_iter(signals.clone())?
                 .filter(f)
-                .into_pipeline_data(ctrlc)),
+                .into_pipeline_data(signals)),
             PipelineData::Value(v, ..) => {
                 if f(&v) {
                     Ok(v.into_pipeline_data())
@@ -583,6 +619,9 @@ impl PipelineData {
             span,
             metadata,
             trim_end_newline,
+            stdout_type,
+            #[cfg(unix)]
+            raw_fd,
         } = self
         {
             let exit_code = exit_code.take();
@@ -598,7 +637,7 @@ impl PipelineData {
             // Or we'll never have a chance to read exit_code if stderr producer produce too much stderr message.
             // So we consume stderr stream and rebuild it.
             let stderr = stderr.map(|stderr_stream| {
-                let stderr_ctrlc = stderr_stream.ctrlc.clone();
+                let stderr_signals = stderr_stream.signals.clone();
                 let stderr_span = stderr_stream.span;
                 let stderr_bytes = stderr_stream
                     .into_bytes()
@@ -606,7 +645,7 @@ impl PipelineData {
                     .unwrap_or_default();
                 RawStream::new(
                     Box::new(std::iter::once(Ok(stderr_bytes))),
-                    stderr_ctrlc,
+                    stderr_signals,
                     stderr_span,
                     None,
                 )
@@ -614,7 +653,7 @@ impl PipelineData {
 
             match exit_code {
                 Some(exit_code_stream) => {
-                    let ctrlc = exit_code_stream.ctrlc.clone();
+                    let signals = exit_code_stream.signals.clone();
                     let exit_code: Vec<Value> = exit_code_stream.into_iter().collect();
                     if let Some(Value::Int { val: code, .. }) = exit_code.last() {
                         // if exit_code is not 0, it indicates error occurred, return back Err.
@@ -626,10 +665,16 @@ impl PipelineData {
                         PipelineData::ExternalStream {
                             stdout: None,
                             stderr,
-                            exit_code: Some(ListStream::from_stream(exit_code.into_iter(), ctrlc)),
+                            exit_code: Some(ListStream::from_stream(
+                                exit_code.into_iter(),
+                                signals,
+                            )),
                             span,
                             metadata,
                             trim_end_newline,
+                            stdout_type,
+                            #[cfg(unix)]
+                            raw_fd,
                         },
                         failed_to_run,
                     )
@@ -642,6 +687,9 @@ impl PipelineData {
                         span,
                         metadata,
                         trim_end_newline,
+                        stdout_type,
+                        #[cfg(unix)]
+                        raw_fd,
                     },
                     failed_to_run,
                 ),
@@ -654,7 +702,12 @@ impl PipelineData {
     /// This is useful to expand Value::Range into array notation, specifically when
     /// converting `to json` or `to nuon`.
     /// `1..3 | to XX -> [1,2,3]`
-    pub fn try_expand_range(self) -> Result<PipelineData, ShellError> {
+    ///
+    /// Bounded ranges are streamed lazily as a `ListStream` rather than collected into a
+    /// `Vec` up front, so a huge range (`1..10_000_000 | to json`) doesn't have to sit fully
+    /// in memory before serialization can start.
+    pub fn try_expand_range(self, signals: impl Into<Signals>) -> Result<PipelineData, ShellError> {
+        let signals = signals.into();
         let input = match self {
             PipelineData::Value(v, metadata) => match v {
                 Value::Range { val, .. } => {
@@ -688,8 +741,8 @@ impl PipelineData {
                         }
                         _ => (),
                     }
-                    let range_values: Vec<Value> = val.into_range_iter(None)?.collect();
-                    PipelineData::Value(Value::list(range_values, span), None)
+                    let range_iter = val.into_range_iter(signals.clone())?;
+                    PipelineData::ListStream(ListStream::from_stream(range_iter, signals), None)
                 }
                 x => PipelineData::Value(x, metadata),
             },
@@ -702,13 +755,16 @@ impl PipelineData {
     ///
     /// `no_newline` controls if we need to attach newline character to output.
     /// `to_stderr` controls if data is output to stderr, when the value is false, the data is output to stdout.
+    ///
+    /// Returns an [`ExitStatus`] rather than a plain exit code so a caller can tell a normal
+    /// nonzero exit apart from termination by signal, not just that something went wrong.
     pub fn print(
         self,
         engine_state: &EngineState,
         stack: &mut Stack,
         no_newline: bool,
         to_stderr: bool,
-    ) -> Result<i64, ShellError> {
+    ) -> Result<ExitStatus, ShellError> {
         // If the table function is in the declarations, then we can use it
         // to create the table value that will be printed in the terminal
 
@@ -727,7 +783,9 @@ impl PipelineData {
         if let Some(decl_id) = engine_state.table_decl_id {
             let command = engine_state.get_decl(decl_id);
             if command.get_block_id().is_some() {
-                return self.write_all_and_flush(engine_state, config, no_newline, to_stderr);
+                return self
+                    .write_all_and_flush(engine_state, config, no_newline, to_stderr)
+                    .map(|code| ExitStatus::Exited(code as i32));
             }
 
             let mut call = Call::new(Span::new(0, 0));
@@ -739,7 +797,7 @@ impl PipelineData {
             self.write_all_and_flush(engine_state, config, no_newline, to_stderr)?;
         };
 
-        Ok(0)
+        Ok(ExitStatus::Exited(0))
     }
 
     /// Consume and print self data immediately.
@@ -761,13 +819,246 @@ impl PipelineData {
             ..
         } = self
         {
-            print_if_stream(stream, stderr_stream, to_stderr, exit_code)
+            print_if_stream(stream, stderr_stream, to_stderr, exit_code).map(|status| status.code())
         } else {
             let config = engine_state.get_config();
             self.write_all_and_flush(engine_state, config, no_newline, to_stderr)
         }
     }
 
+    /// Like [`print`](Self::print), but instead of writing the rendered output to the real
+    /// stdout/stderr file descriptors, captures it and hands it back as a `Value`. This lets a
+    /// host program embedding nu get at a command's formatted output without shelling out or
+    /// hijacking process file descriptors.
+    ///
+    /// Runs the same `table`-decl formatting as `print` for ordinary data, sharing its
+    /// per-item rendering logic but collecting the result into an in-memory buffer instead of
+    /// writing it out. For an external command's stream, stdout is captured via
+    /// [`collapse_bytes`] and returned as a `Value::String`/`Value::Binary` according to its
+    /// [`ByteStreamType`]; stderr and the exit code are drained exactly as `print` drains them,
+    /// by delegating to [`print_if_stream`] with no stdout stream of its own to consume.
+    pub fn capture(
+        self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        no_newline: bool,
+    ) -> Result<(Value, i64), ShellError> {
+        let config = engine_state.get_config();
+
+        if let PipelineData::ExternalStream {
+            stdout: stream,
+            stderr: stderr_stream,
+            exit_code,
+            stdout_type,
+            trim_end_newline,
+            ..
+        } = self
+        {
+            let captured = match stream {
+                Some(stream) => collapse_bytes(stream, stdout_type, trim_end_newline),
+                None => Value::string(String::new(), Span::unknown()),
+            };
+            let exit = print_if_stream(None, stderr_stream, false, exit_code)?;
+            return Ok((captured, exit.code()));
+        }
+
+        if let Some(decl_id) = engine_state.table_decl_id {
+            let command = engine_state.get_decl(decl_id);
+            if command.get_block_id().is_some() {
+                return Ok((self.capture_all(engine_state, config, no_newline), 0));
+            }
+
+            let mut call = Call::new(Span::new(0, 0));
+            call.redirect_stdout = false;
+            let table = command.run(engine_state, stack, &call, self)?;
+
+            Ok((table.capture_all(engine_state, config, no_newline), 0))
+        } else {
+            Ok((self.capture_all(engine_state, config, no_newline), 0))
+        }
+    }
+
+    /// Serialize `self` as a sequence of length-framed records written to `writer`, so a
+    /// `ListStream` or `ExternalStream` can cross a pipe or process boundary lazily instead of
+    /// first being collected with [`PipelineData::into_value`]. Pair with
+    /// [`PipelineData::read_framed`] on the far end of `writer`.
+    ///
+    /// A `ListStream`'s items are written out as they're produced, one `Value` frame per item;
+    /// reading an item from the far end's iterator drives how much of the stream actually gets
+    /// written, so the writer never buffers more of the stream than the reader has pulled. An
+    /// `ExternalStream`'s stdout and stderr chunks are written as raw bytes (no serialization
+    /// cost per chunk) with the stream's `ByteStreamType` and trailing-newline flag carried in
+    /// the header so the far end can collapse them the same way [`collapse_bytes`] does here,
+    /// and its exit code is forwarded as a final `Value` once the underlying stream produces
+    /// one. `signals` is checked between items, so a producer loop bails out with a real error
+    /// rather than silently writing out a truncated stream nobody wants anymore.
+    pub fn write_framed(
+        self,
+        writer: &mut impl Write,
+        signals: impl Into<Signals>,
+    ) -> Result<(), ShellError> {
+        let signals = signals.into();
+        match self {
+            PipelineData::Empty => {
+                write_header(writer, None, false, ByteStreamType::Unknown, false)?;
+            }
+            PipelineData::Value(value, metadata) => {
+                write_header(writer, metadata, false, ByteStreamType::Unknown, false)?;
+                write_value_frame(writer, FrameTag::Value, &value)?;
+            }
+            PipelineData::ListStream(stream, metadata) => {
+                write_header(writer, metadata, false, ByteStreamType::Unknown, false)?;
+                for value in stream {
+                    signals.check(Span::unknown())?;
+                    write_value_frame(writer, FrameTag::Value, &value)?;
+                }
+            }
+            PipelineData::ExternalStream {
+                stdout,
+                stderr,
+                exit_code,
+                metadata,
+                trim_end_newline,
+                stdout_type,
+                ..
+            } => {
+                write_header(writer, metadata, true, stdout_type, trim_end_newline)?;
+
+                if let Some(stdout) = stdout {
+                    write_raw_stream(writer, stdout, FrameTag::StdoutChunk, &signals)?;
+                }
+
+                if let Some(stderr) = stderr {
+                    write_raw_stream(writer, stderr, FrameTag::StderrChunk, &signals)?;
+                }
+
+                if let Some(exit_code) = exit_code {
+                    for value in exit_code {
+                        write_value_frame(writer, FrameTag::ExitCode, &value)?;
+                    }
+                }
+            }
+        }
+
+        write_frame(writer, FrameTag::End, &[])
+    }
+
+    /// Reconstruct a lazy `PipelineData` from a byte stream previously produced by
+    /// [`PipelineData::write_framed`], pulling one record at a time as the result is consumed
+    /// rather than buffering the whole stream up front. `signals` is checked before each record
+    /// read, so a reader stuck behind a stalled producer can still be cancelled.
+    ///
+    /// Returns a `ListStream` if the far end wrote one, or an `ExternalStream` with its stdout,
+    /// stderr, and exit code each reconstructed as independently-lazy streams over the shared
+    /// reader.
+    pub fn read_framed<R>(
+        mut reader: R,
+        signals: impl Into<Signals>,
+    ) -> Result<PipelineData, ShellError>
+    where
+        R: Read + Send + 'static,
+    {
+        let signals = signals.into();
+        let (tag, payload) = read_frame(&mut reader)?;
+        if tag != FrameTag::Header {
+            return Err(ShellError::IOError {
+                msg: "framed pipeline stream is missing its header record".into(),
+            });
+        }
+        let header: FrameHeader =
+            serde_json::from_slice(&payload).map_err(|err| ShellError::IOError {
+                msg: format!("failed to decode pipeline frame header: {err}"),
+            })?;
+
+        if !header.external {
+            let values_signals = signals.clone();
+            let values = std::iter::from_fn(move || {
+                if let Err(err) = values_signals.check(Span::unknown()) {
+                    return Some(Value::error(err, Span::unknown()));
+                }
+                match read_frame(&mut reader) {
+                    Ok((FrameTag::Value, payload)) => Some(read_value_frame(&payload)),
+                    Ok((FrameTag::End, _)) => None,
+                    Ok((other, _)) => Some(Value::error(
+                        ShellError::IOError {
+                            msg: format!("unexpected {other:?} frame in pipeline value stream"),
+                        },
+                        Span::unknown(),
+                    )),
+                    Err(err) => Some(Value::error(err, Span::unknown())),
+                }
+            });
+            return Ok(PipelineData::ListStream(
+                ListStream::from_stream(values, signals),
+                header.metadata,
+            ));
+        }
+
+        let source = Arc::new(Mutex::new(FrameSource {
+            reader,
+            pending: None,
+        }));
+
+        let stdout = {
+            let source = Arc::clone(&source);
+            let signals = signals.clone();
+            RawStream::new(
+                Box::new(std::iter::from_fn(move || {
+                    if signals.interrupted() {
+                        return None;
+                    }
+                    next_chunk(&source, FrameTag::StdoutChunk)
+                })),
+                signals,
+                Span::unknown(),
+                None,
+            )
+        };
+
+        let stderr = {
+            let source = Arc::clone(&source);
+            let signals = signals.clone();
+            RawStream::new(
+                Box::new(std::iter::from_fn(move || {
+                    if signals.interrupted() {
+                        return None;
+                    }
+                    next_chunk(&source, FrameTag::StderrChunk)
+                })),
+                signals,
+                Span::unknown(),
+                None,
+            )
+        };
+
+        let exit_code = {
+            let source = Arc::clone(&source);
+            ListStream::from_stream(
+                std::iter::from_fn(move || {
+                    let mut source = source.lock().expect("pipeline frame source poisoned");
+                    match source.take(FrameTag::ExitCode) {
+                        Ok(Some(payload)) => Some(read_value_frame(&payload)),
+                        Ok(None) => None,
+                        Err(err) => Some(Value::error(err, Span::unknown())),
+                    }
+                }),
+                signals,
+            )
+        };
+
+        Ok(PipelineData::ExternalStream {
+            stdout: Some(stdout),
+            stderr: Some(stderr),
+            exit_code: Some(exit_code),
+            span: Span::unknown(),
+            metadata: header.metadata,
+            trim_end_newline: header.trim_end_newline,
+            stdout_type: header.stdout_type,
+            #[cfg(unix)]
+            raw_fd: None,
+        })
+    }
+
     fn write_all_and_flush(
         self,
         engine_state: &EngineState,
@@ -776,21 +1067,7 @@ impl PipelineData {
         to_stderr: bool,
     ) -> Result<i64, ShellError> {
         for item in self {
-            let mut is_err = false;
-            let mut out = if let Value::Error { error, .. } = item {
-                let working_set = StateWorkingSet::new(engine_state);
-                // Value::Errors must always go to stderr, not stdout.
-                is_err = true;
-                format_error(&working_set, &*error)
-            } else if no_newline {
-                item.to_expanded_string("", config)
-            } else {
-                item.to_expanded_string("\n", config)
-            };
-
-            if !no_newline {
-                out.push('\n');
-            }
+            let (out, is_err) = format_print_item(item, engine_state, config, no_newline);
 
             if !to_stderr && !is_err {
                 stdout_write_all_and_flush(out)?
@@ -801,6 +1078,263 @@ impl PipelineData {
 
         Ok(0)
     }
+
+    /// Same formatting as [`write_all_and_flush`], but collecting the rendered text into an
+    /// in-memory `String` instead of writing it to the real stdout/stderr file descriptors.
+    /// Backs [`PipelineData::capture`].
+    fn capture_all(self, engine_state: &EngineState, config: &Config, no_newline: bool) -> Value {
+        let mut out = String::new();
+        for item in self {
+            let (rendered, _) = format_print_item(item, engine_state, config, no_newline);
+            out.push_str(&rendered);
+        }
+
+        Value::string(out, Span::unknown())
+    }
+}
+
+/// Render a single pipeline item the way [`PipelineData::write_all_and_flush`] and
+/// [`PipelineData::capture`] both do: `Value::Error`s go through [`format_error`] (and are
+/// reported as such via the returned `bool`), everything else through
+/// [`Value::to_expanded_string`], with a trailing newline appended unless `no_newline` is set.
+fn format_print_item(
+    item: Value,
+    engine_state: &EngineState,
+    config: &Config,
+    no_newline: bool,
+) -> (String, bool) {
+    let mut is_err = false;
+    let mut out = if let Value::Error { error, .. } = item {
+        let working_set = StateWorkingSet::new(engine_state);
+        // Value::Errors must always go to stderr, not stdout.
+        is_err = true;
+        format_error(&working_set, &*error)
+    } else if no_newline {
+        item.to_expanded_string("", config)
+    } else {
+        item.to_expanded_string("\n", config)
+    };
+
+    if !no_newline {
+        out.push('\n');
+    }
+
+    (out, is_err)
+}
+
+/// A single record in the framed wire protocol [`PipelineData::write_framed`] and
+/// [`PipelineData::read_framed`] use to move a `ListStream` or `ExternalStream` across a pipe
+/// or process boundary. On the wire a record is this tag as one byte, a little-endian `u32`
+/// payload length, then the payload, so a reader can pull one record off the pipe at a time and
+/// apply real backpressure to a stalled producer instead of everything having to be buffered up
+/// front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameTag {
+    /// One-time header, always first: carries the `PipelineMetadata` and whether the records
+    /// that follow reconstruct a `ListStream` or an `ExternalStream`.
+    Header,
+    /// A single `Value`. Used for `ListStream` items and for the trailing exit code, and doubles
+    /// as an error marker for a raw stdout/stderr chunk: seeing one while reconstructing an
+    /// `ExternalStream`'s stdout or stderr closes that particular stream with the carried
+    /// `Value::Error`.
+    Value,
+    /// A chunk of an external command's raw stdout bytes.
+    StdoutChunk,
+    /// A chunk of an external command's raw stderr bytes.
+    StderrChunk,
+    /// The external command's exit code, carried as a `Value` frame.
+    ExitCode,
+    /// No further records follow; the stream is complete.
+    End,
+}
+
+impl FrameTag {
+    fn to_byte(self) -> u8 {
+        match self {
+            FrameTag::Header => 0,
+            FrameTag::Value => 1,
+            FrameTag::StdoutChunk => 2,
+            FrameTag::StderrChunk => 3,
+            FrameTag::ExitCode => 4,
+            FrameTag::End => 5,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<FrameTag, ShellError> {
+        match byte {
+            0 => Ok(FrameTag::Header),
+            1 => Ok(FrameTag::Value),
+            2 => Ok(FrameTag::StdoutChunk),
+            3 => Ok(FrameTag::StderrChunk),
+            4 => Ok(FrameTag::ExitCode),
+            5 => Ok(FrameTag::End),
+            other => Err(ShellError::IOError {
+                msg: format!("invalid pipeline frame tag {other}"),
+            }),
+        }
+    }
+}
+
+/// The header record written once at the start of a framed stream, describing the shape the
+/// records that follow reconstruct on the reading side. See [`PipelineData::write_framed`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FrameHeader {
+    metadata: Option<PipelineMetadata>,
+    external: bool,
+    stdout_type: ByteStreamType,
+    trim_end_newline: bool,
+}
+
+fn write_frame(writer: &mut impl Write, tag: FrameTag, payload: &[u8]) -> Result<(), ShellError> {
+    let io_err = |err: std::io::Error| ShellError::IOError {
+        msg: err.to_string(),
+    };
+    writer.write_all(&[tag.to_byte()]).map_err(io_err)?;
+    writer
+        .write_all(&(payload.len() as u32).to_le_bytes())
+        .map_err(io_err)?;
+    writer.write_all(payload).map_err(io_err)
+}
+
+fn write_value_frame(
+    writer: &mut impl Write,
+    tag: FrameTag,
+    value: &Value,
+) -> Result<(), ShellError> {
+    let payload = serde_json::to_vec(value).map_err(|err| ShellError::IOError {
+        msg: format!("failed to encode pipeline frame: {err}"),
+    })?;
+    write_frame(writer, tag, &payload)
+}
+
+fn write_header(
+    writer: &mut impl Write,
+    metadata: Option<PipelineMetadata>,
+    external: bool,
+    stdout_type: ByteStreamType,
+    trim_end_newline: bool,
+) -> Result<(), ShellError> {
+    let header = FrameHeader {
+        metadata,
+        external,
+        stdout_type,
+        trim_end_newline,
+    };
+    let payload = serde_json::to_vec(&header).map_err(|err| ShellError::IOError {
+        msg: format!("failed to encode pipeline frame header: {err}"),
+    })?;
+    write_frame(writer, FrameTag::Header, &payload)
+}
+
+/// Write a `RawStream`'s raw byte chunks out as frames tagged `tag`, without going through the
+/// `Value` conversion its own `Iterator` impl applies, so framing a stdout/stderr chunk costs no
+/// more than the copy onto the wire. An error partway through is written as a single `Value`
+/// frame and ends the sub-stream, mirroring how [`PipelineData::read_framed`] turns it back into
+/// a terminal `Err` on the far end.
+fn write_raw_stream(
+    writer: &mut impl Write,
+    stream: RawStream,
+    tag: FrameTag,
+    signals: &Signals,
+) -> Result<(), ShellError> {
+    let RawStream {
+        stream, leftover, ..
+    } = stream;
+
+    if !leftover.is_empty() {
+        write_frame(writer, tag, &leftover)?;
+    }
+
+    for chunk in stream {
+        signals.check(Span::unknown())?;
+        match chunk {
+            Ok(bytes) => write_frame(writer, tag, &bytes)?,
+            Err(err) => {
+                write_value_frame(writer, FrameTag::Value, &Value::error(err, Span::unknown()))?;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_frame(reader: &mut impl Read) -> Result<(FrameTag, Vec<u8>), ShellError> {
+    let io_err = |err: std::io::Error| ShellError::IOError {
+        msg: err.to_string(),
+    };
+    let mut tag_byte = [0u8; 1];
+    reader.read_exact(&mut tag_byte).map_err(io_err)?;
+    let tag = FrameTag::from_byte(tag_byte[0])?;
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes).map_err(io_err)?;
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).map_err(io_err)?;
+    Ok((tag, payload))
+}
+
+fn read_value_frame(payload: &[u8]) -> Value {
+    serde_json::from_slice(payload).unwrap_or_else(|err| {
+        Value::error(
+            ShellError::IOError {
+                msg: format!("failed to decode pipeline frame: {err}"),
+            },
+            Span::unknown(),
+        )
+    })
+}
+
+/// A frame reader shared by an `ExternalStream`'s reconstructed stdout, stderr, and exit-code
+/// streams, so they can all pull from the one underlying [`Read`] without racing. `take` peeks
+/// one frame ahead: a frame that doesn't belong to the requested sub-stream is buffered in
+/// `pending` for whichever sub-stream asks for it next, rather than being dropped.
+struct FrameSource<R> {
+    reader: R,
+    pending: Option<(FrameTag, Vec<u8>)>,
+}
+
+impl<R: Read> FrameSource<R> {
+    fn next_frame(&mut self) -> Result<(FrameTag, Vec<u8>), ShellError> {
+        match self.pending.take() {
+            Some(frame) => Ok(frame),
+            None => read_frame(&mut self.reader),
+        }
+    }
+
+    /// Pull the next record if it's tagged `tag`. A record tagged differently is buffered for a
+    /// later call and `Ok(None)` is returned, signalling the end of this particular sub-stream.
+    fn take(&mut self, tag: FrameTag) -> Result<Option<Vec<u8>>, ShellError> {
+        let (frame_tag, payload) = self.next_frame()?;
+        if frame_tag == tag {
+            Ok(Some(payload))
+        } else {
+            self.pending = Some((frame_tag, payload));
+            Ok(None)
+        }
+    }
+}
+
+/// Pull the next raw chunk for an `ExternalStream`'s stdout or stderr (selected by `tag`) out of
+/// a shared [`FrameSource`], translating a `Value::Error` marker frame into the chunk stream's
+/// terminal `Err` so `drain` still surfaces it.
+fn next_chunk<R: Read>(
+    source: &Arc<Mutex<FrameSource<R>>>,
+    tag: FrameTag,
+) -> Option<Result<Vec<u8>, ShellError>> {
+    let mut source = source.lock().expect("pipeline frame source poisoned");
+    match source.take(tag) {
+        Ok(Some(bytes)) => Some(Ok(bytes)),
+        Ok(None) => match source.take(FrameTag::Value) {
+            Ok(Some(payload)) => match read_value_frame(&payload) {
+                Value::Error { error, .. } => Some(Err(*error)),
+                _ => None,
+            },
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        },
+        Err(err) => Some(Err(err)),
+    }
 }
 
 pub struct PipelineIterator(PipelineData);
@@ -845,31 +1379,45 @@ pub fn print_if_stream(
     stderr_stream: Option<RawStream>,
     to_stderr: bool,
     exit_code: Option<ListStream>,
-) -> Result<i64, ShellError> {
-    if let Some(stderr_stream) = stderr_stream {
-        // Write stderr to our stderr, if it's present
-        thread::Builder::new()
-            .name("stderr consumer".to_string())
-            .spawn(move || {
-                let RawStream {
-                    stream,
-                    leftover,
-                    ctrlc,
-                    ..
-                } = stderr_stream;
-                let mut stderr = std::io::stderr();
-                let _ = stderr.write_all(&leftover);
-                drop(leftover);
-                for bytes in stream {
-                    if nu_utils::ctrl_c::was_pressed(&ctrlc) {
-                        break;
-                    }
-                    if let Ok(bytes) = bytes {
-                        let _ = stderr.write_all(&bytes);
+) -> Result<ExitStatus, ShellError> {
+    // Read stdout and stderr concurrently, each on its own thread: if the external command
+    // fills both pipe buffers at once, draining only one of them here while the other sits
+    // unread would deadlock the child. The stderr consumer runs on a worker thread while
+    // stdout is drained inline; its `JoinHandle` is kept so we can join it (and surface any
+    // I/O error it hit) before `drain_exit_code` runs, guaranteeing stderr is fully flushed
+    // first.
+    let stderr_handle = stderr_stream
+        .map(|stderr_stream| {
+            thread::Builder::new()
+                .name("stderr consumer".to_string())
+                .spawn(move || -> Result<(), ShellError> {
+                    let RawStream {
+                        stream,
+                        leftover,
+                        signals,
+                        ..
+                    } = stderr_stream;
+                    let mut stderr = std::io::stderr();
+                    stderr
+                        .write_all(&leftover)
+                        .map_err(|err| ShellError::IOError {
+                            msg: err.to_string(),
+                        })?;
+                    drop(leftover);
+                    for bytes in stream {
+                        if signals.interrupted() {
+                            break;
+                        }
+                        stderr
+                            .write_all(&bytes?)
+                            .map_err(|err| ShellError::IOError {
+                                msg: err.to_string(),
+                            })?;
                     }
-                }
-            })?;
-    }
+                    Ok(())
+                })
+        })
+        .transpose()?;
 
     if let Some(stream) = stream {
         for s in stream {
@@ -884,21 +1432,106 @@ pub fn print_if_stream(
         }
     }
 
+    if let Some(stderr_handle) = stderr_handle {
+        stderr_handle.join().map_err(|_| ShellError::IOError {
+            msg: "stderr consumer thread panicked".into(),
+        })??;
+    }
+
     // Make sure everything has finished
     if let Some(exit_code) = exit_code {
         return drain_exit_code(exit_code);
     }
 
-    Ok(0)
+    Ok(ExitStatus::Exited(0))
+}
+
+/// Rich exit-status information for an external command, preserving what a plain exit-code
+/// `i64` throws away: whether the process ran to completion, was killed by a signal, or
+/// reported a status this platform doesn't model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The process ran to completion and exited with this code.
+    Exited(i32),
+    /// The process was terminated by a signal before it could exit normally.
+    Signaled { signal: i32, core_dumped: bool },
+    /// A status that doesn't decode as either of the above, e.g. a code outside `i32`'s range.
+    Unknown(i64),
 }
 
-fn drain_exit_code(exit_code: ListStream) -> Result<i64, ShellError> {
+impl ExitStatus {
+    /// Decode a raw unix `wait(2)` status: the low 7 bits hold the terminating signal (0 means
+    /// the process exited normally), bit `0x80` marks a core dump, and otherwise the exit code
+    /// sits in the high byte.
+    #[cfg(unix)]
+    fn from_wait_status(status: i64) -> ExitStatus {
+        let status = status as i32;
+        let signal = status & 0x7f;
+        if signal != 0 {
+            ExitStatus::Signaled {
+                signal,
+                core_dumped: status & 0x80 != 0,
+            }
+        } else {
+            ExitStatus::Exited((status >> 8) & 0xff)
+        }
+    }
+
+    /// Collapse back down to a plain numeric code, for callers that only care about zero vs.
+    /// nonzero or need to keep returning a historical `i64` exit code.
+    pub fn code(&self) -> i64 {
+        match self {
+            ExitStatus::Exited(code) => *code as i64,
+            ExitStatus::Signaled { signal, .. } => 128 + *signal as i64,
+            ExitStatus::Unknown(code) => *code,
+        }
+    }
+}
+
+fn drain_exit_code(exit_code: ListStream) -> Result<ExitStatus, ShellError> {
     let mut exit_codes: Vec<_> = exit_code.into_iter().collect();
     match exit_codes.pop() {
-        #[cfg(unix)]
         Some(Value::Error { error, .. }) => Err(*error),
-        Some(Value::Int { val, .. }) => Ok(val),
-        _ => Ok(0),
+        #[cfg(unix)]
+        Some(Value::Int { val, .. }) => Ok(ExitStatus::from_wait_status(val)),
+        #[cfg(not(unix))]
+        Some(Value::Int { val, .. }) => match i32::try_from(val) {
+            Ok(code) => Ok(ExitStatus::Exited(code)),
+            Err(_) => Ok(ExitStatus::Unknown(val)),
+        },
+        _ => Ok(ExitStatus::Exited(0)),
+    }
+}
+
+/// Collect an external command's stdout stream into a single `Value`, honoring its
+/// [`ByteStreamType`] instead of re-sniffing the bytes at every call site.
+///
+/// `Binary` never attempts string coercion. `String` and `Unknown` both sniff for valid UTF-8 -
+/// `stdout_type` can come straight off a deserialized frame header from the framed wire protocol
+/// (see [`FrameHeader`]), so a `String` tag is only ever a hint, never a guarantee: a
+/// buggy or hostile plugin could tag a stream `String` while sending bytes that aren't valid
+/// UTF-8, and trusting that tag for an `expect()` would panic the whole host process. Falling
+/// back to `Binary` instead costs nothing extra, since `Unknown` already has to do the same check
+/// to decide. `trim_end_newline` only ever applies to the string case; for binary the caller may
+/// need the original bytes untouched.
+fn collapse_bytes(stream: RawStream, stdout_type: ByteStreamType, trim_end_newline: bool) -> Value {
+    let collected = match stream.into_bytes() {
+        Ok(collected) => collected,
+        Err(err) => return Value::error(err, Span::unknown()),
+    };
+
+    match stdout_type {
+        ByteStreamType::Binary => Value::binary(collected.item, collected.span),
+        ByteStreamType::String | ByteStreamType::Unknown => match String::from_utf8(collected.item)
+        {
+            Ok(mut output) => {
+                if trim_end_newline {
+                    output.truncate(output.trim_end_matches(LINE_ENDING_PATTERN).len());
+                }
+                Value::string(output, collected.span)
+            }
+            Err(err) => Value::binary(err.into_bytes(), collected.span),
+        },
     }
 }
 
@@ -952,11 +1585,11 @@ where
 }
 
 pub trait IntoInterruptiblePipelineData {
-    fn into_pipeline_data(self, ctrlc: Option<Arc<AtomicBool>>) -> PipelineData;
+    fn into_pipeline_data(self, signals: impl Into<Signals>) -> PipelineData;
     fn into_pipeline_data_with_metadata(
         self,
         metadata: impl Into<Option<PipelineMetadata>>,
-        ctrlc: Option<Arc<AtomicBool>>,
+        signals: impl Into<Signals>,
     ) -> PipelineData;
 }
 
@@ -966,9 +1599,9 @@ where
     I::IntoIter: Send + 'static,
     <I::IntoIter as Iterator>::Item: Into<Value>,
 {
-    fn into_pipeline_data(self, ctrlc: Option<Arc<AtomicBool>>) -> PipelineData {
+    fn into_pipeline_data(self, signals: impl Into<Signals>) -> PipelineData {
         PipelineData::ListStream(
-            ListStream::from_stream(self.into_iter().map(Into::into), ctrlc),
+            ListStream::from_stream(self.into_iter().map(Into::into), signals),
             None,
         )
     }
@@ -976,10 +1609,10 @@ where
     fn into_pipeline_data_with_metadata(
         self,
         metadata: impl Into<Option<PipelineMetadata>>,
-        ctrlc: Option<Arc<AtomicBool>>,
+        signals: impl Into<Signals>,
     ) -> PipelineData {
         PipelineData::ListStream(
-            ListStream::from_stream(self.into_iter().map(Into::into), ctrlc),
+            ListStream::from_stream(self.into_iter().map(Into::into), signals),
             metadata.into(),
         )
     }
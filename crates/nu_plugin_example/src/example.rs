@@ -1,5 +1,7 @@
-use nu_plugin::{EvaluatedCall, LabeledError};
-use nu_protocol::{NuString, Value};
+use nu_plugin::{EngineInterface, EvaluatedCall, Label, LabeledError};
+use nu_protocol::{
+    engine::Closure, IntoInterruptiblePipelineData, NuString, PipelineData, Spanned, Value,
+};
 
 pub struct Example;
 
@@ -7,25 +9,36 @@ impl Example {
     pub fn print_values(
         &self,
         index: u32,
+        engine: &EngineInterface,
         call: &EvaluatedCall,
-        input: &Value,
-    ) -> Result<(), LabeledError> {
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
         // Note. When debugging your plugin, you may want to print something to the console
         // Use the eprintln macro to print your messages. Trying to print to stdout will
         // cause a decoding error for your message
         eprintln!("Calling test {index} signature");
-        eprintln!("value received {input:?}");
 
         // To extract the arguments from the Call object you can use the functions req, has_flag,
         // opt, rest, and get_flag
-        //
-        // Note that plugin calls only accept simple arguments, this means that you can
-        // pass to the plug in Int and String. This should be improved when the plugin has
-        // the ability to call back to NuShell to extract more information
-        // Keep this in mind when designing your plugin signatures
         let a: i64 = call.req(0)?;
         let b: NuString = call.req(1)?;
         let flag = call.has_flag("flag")?;
+
+        // A negative `a` can't be combined with `--flag` in this example. Point at both
+        // of the offending arguments in the same diagnostic, rather than just the first one we
+        // noticed, so the user can see the whole conflict at once.
+        if flag && a < 0 {
+            return Err(LabeledError {
+                msg: "`a` and `--flag` can't be used together when `a` is negative".into(),
+                labels: vec![
+                    Label::new("this value is negative", call.positional_nth_span(0)?),
+                    Label::new("conflicts with this flag", call.get_flag_span("flag")?),
+                ],
+                code: Some("nu_plugin_example::conflicting_args".into()),
+                help: Some("pass a non-negative `a`, or drop `--flag`".into()),
+                ..Default::default()
+            });
+        }
         let opt: Option<i64> = call.opt(2)?;
         let named: Option<NuString> = call.get_flag("named")?;
         let rest: Vec<NuString> = call.rest(3)?;
@@ -48,6 +61,29 @@ impl Example {
             eprintln!("No named value found")
         }
 
-        Ok(())
+        // Environment variables are also no longer out of reach.
+        if let Some(home) = engine.get_env_var("HOME")? {
+            eprintln!("engine reports HOME = {home:?}");
+        }
+        engine.add_env_var("NU_PLUGIN_EXAMPLE_LAST_INDEX", Value::int(index as i64, call.head))?;
+
+        // `input` may be a `ListStream` backed by an unbounded source (e.g. `open big.log | lines`).
+        // Pulling from it lazily, one `Value` at a time, means we never have to hold the whole
+        // pipeline in memory - the engine applies backpressure on our behalf via the stream's
+        // flow control.
+        let each = call.get_flag::<Spanned<Closure>>("each")?;
+        let engine = engine.clone();
+        let call_head = call.head;
+        let output = input.into_iter().map(move |value| {
+            eprintln!("value received {value:?}");
+            match &each {
+                Some(closure) => engine
+                    .eval_closure(closure, vec![value.clone()], Some(value.clone()))
+                    .unwrap_or_else(|err| Value::error(err, call_head)),
+                None => value,
+            }
+        });
+
+        Ok(output.into_pipeline_data(None))
     }
 }